@@ -37,15 +37,20 @@ fn main() -> Result<()> {
                 Type::Bitmask => {
                     println!("      Type    : Bitmask");
                 }
-                Type::Menu(items) => {
+                Type::Menu { items, default } => {
                     println!("      Type    : Menu ==>");
-                    for item in items {
+                    for (i, item) in items.iter().enumerate() {
+                        let marker = if Some(i) == *default {
+                            " (default)"
+                        } else {
+                            ""
+                        };
                         match item {
                             MenuItem::String(str) => {
-                                println!("       - {}", str);
+                                println!("       - {}{}", str, marker);
                             }
                             MenuItem::Number(val) => {
-                                println!("       - {}", val);
+                                println!("       - {}{}", val, marker);
                             }
                         }
                     }
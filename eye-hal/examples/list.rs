@@ -11,6 +11,7 @@ fn main() -> Result<()> {
         for desc in list {
             println!("{}", desc.uri);
             println!("  product : {}", desc.product);
+            println!("  exclusive: {}", desc.exclusive);
         }
     }
 
@@ -9,12 +9,10 @@ fn main() -> Result<()> {
     // Create a context
     let ctx = PlatformContext::default();
 
-    // Query for available devices.
-    let devices = ctx.devices()?;
-
     // First, we need a capture device to read images from. For this example, let's just choose
     // whatever device is first in the list.
-    let dev = ctx.open_device(&devices[0].uri)?;
+    let device = ctx.first_device()?;
+    let dev = ctx.open_device(&device.uri)?;
 
     // Query for available streams and just choose the first one.
     let streams = dev.streams()?;
@@ -24,10 +22,7 @@ fn main() -> Result<()> {
     // Since we want to capture images, we need to access the native image stream of the device.
     // The backend will internally select a suitable implementation for the platform stream. On
     // Linux for example, most devices support memory-mapped buffers.
-    let mut stream = dev.start_stream(DeviceStreamSettings {
-        desc: &stream_desc,
-        buffers_count: None,
-    })?;
+    let mut stream = dev.start_stream(DeviceStreamSettings::new(&stream_desc))?;
 
     // Here we create a loop and just capture images as long as the device produces them. Normally,
     // this loop will run forever unless we unplug the camera or exit the program.
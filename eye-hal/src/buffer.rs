@@ -1,14 +1,91 @@
-use std::borrow::Cow;
+use alloc::{borrow::Cow, format, vec, vec::Vec};
+use core::time::Duration;
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::format::PixelFormat;
+use crate::stream::{Descriptor, FieldOrder};
 
 /// Buffer abstraction
 ///
-/// TODO: multiple planes
+/// The planes of planar images are stored contiguously, use [`Buffer::plane`] to access them
+/// individually.
 #[derive(Clone)]
 pub struct Buffer<'a> {
     inner: Cow<'a, [u8]>,
+    meta: Metadata,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Per-buffer information reported by the driver
+pub struct Metadata {
+    /// Frame sequence number, can be used to match buffers of different streams
+    pub sequence: Option<u32>,
+    /// Capture time relative to the start of the stream
+    ///
+    /// The timestamps are always taken from a monotonic clock, so they are not affected by changes
+    /// to the system time and can be used as presentation timestamps (e.g. for RTP).
+    pub timestamp: Option<Duration>,
+    /// Absolute capture time as read from `clock`
+    ///
+    /// Use this to correlate frames with other sensors (e.g. an IMU) which use the same clock.
+    pub capture_time: Option<Duration>,
+    /// Clock of `capture_time`
+    pub clock: Option<Clock>,
+    /// Point in time at which the driver took the timestamp
+    pub timestamp_source: Option<TimestampSource>,
+    /// Coding type of the frame, only known for compressed formats
+    pub frame_type: Option<FrameType>,
+    /// Field (or fields) of the image contained in the buffer
+    pub field: Option<FieldOrder>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// System clocks
+pub enum Clock {
+    /// Monotonic clock, subject to frequency adjustments (e.g. by NTP)
+    #[default]
+    Monotonic,
+    /// Monotonic clock without any frequency adjustments
+    MonotonicRaw,
+    /// Monotonic clock which includes the time the system was suspended
+    Boottime,
+    /// Wall clock time
+    Realtime,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Point in time at which a frame was timestamped
+pub enum TimestampSource {
+    /// Start of exposure
+    StartOfExposure,
+    /// End of frame, i.e. when the last pixel was received
+    EndOfFrame,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Coding type of a compressed frame
+pub enum FrameType {
+    /// Keyframe (I-frame), can be decoded on its own
+    Key,
+    /// Predicted frame (P-frame), references earlier frames
+    Predicted,
+    /// Bidirectionally predicted frame (B-frame), references earlier and later frames
+    Bidirectional,
 }
 
 impl<'a> Buffer<'a> {
+    /// Attaches metadata to the buffer
+    pub fn with_meta(mut self, meta: Metadata) -> Self {
+        self.meta = meta;
+        self
+    }
+
+    /// Returns the buffer metadata
+    pub fn meta(&self) -> &Metadata {
+        &self.meta
+    }
+
     /// Returns the raw bytes
     pub fn as_bytes(&self) -> &[u8] {
         match &self.inner {
@@ -17,6 +94,11 @@ impl<'a> Buffer<'a> {
         }
     }
 
+    /// Returns the raw bytes as a vector, they are only copied if the buffer borrows them
+    pub fn into_vec(self) -> Vec<u8> {
+        self.inner.into_owned()
+    }
+
     /// Returns the raw bytes
     pub fn into_bytes(self) -> impl Iterator<Item = u8> {
         match self.inner {
@@ -25,6 +107,171 @@ impl<'a> Buffer<'a> {
         }
     }
 
+    /// Returns the luma (grayscale) plane of the image
+    ///
+    /// The luma plane of 8-bit grayscale and planar YUV 4:2:0 images is returned without copying
+    /// any data. YUYV samples are extracted and RGB images converted using the BT.601
+    /// coefficients, both of which are still much cheaper than a full color conversion. The buffer
+    /// data is expected to be tightly packed, i.e. without any row padding.
+    pub fn luma(&self, desc: &Descriptor) -> Result<Cow<'_, [u8]>> {
+        let bytes = self.as_bytes();
+        let pixels = desc.width as usize * desc.height as usize;
+        let (bytes_per_pixel, rgb) = match desc.pixfmt {
+            // planar formats start with the Y plane
+            PixelFormat::Gray(8) | PixelFormat::Nv12 | PixelFormat::I420 | PixelFormat::Yv12 => {
+                (1, None)
+            }
+            PixelFormat::Yuyv => (2, None),
+            PixelFormat::Rgb(24) => (3, Some((0, 1, 2))),
+            // RGBA
+            PixelFormat::Rgb(32) => (4, Some((0, 1, 2))),
+            PixelFormat::Bgr(24) => (3, Some((2, 1, 0))),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::NotSupported,
+                    format!("cannot extract luma from {}", desc.pixfmt),
+                ))
+            }
+        };
+
+        let len = match desc.pixfmt.planes(desc.width, desc.height) {
+            Some(planes) => planes.len,
+            None => pixels * bytes_per_pixel,
+        };
+        if bytes.len() < len {
+            return Err(Error::new(ErrorKind::Other, "buffer too small"));
+        }
+
+        let luma = match (&desc.pixfmt, rgb) {
+            (PixelFormat::Yuyv, _) => Cow::Owned(bytes[..len].iter().step_by(2).copied().collect()),
+            (_, Some((r, g, b))) => Cow::Owned(
+                bytes[..len]
+                    .chunks_exact(bytes_per_pixel)
+                    .map(|px| {
+                        let y = 77 * px[r] as u32 + 150 * px[g] as u32 + 29 * px[b] as u32;
+                        (y >> 8) as u8
+                    })
+                    .collect(),
+            ),
+            _ => Cow::Borrowed(&bytes[..pixels]),
+        };
+
+        Ok(luma)
+    }
+
+    /// Returns the number of planes of an image in the given format
+    ///
+    /// NV12 images consist of a Y and an interleaved UV plane, I420 and YV12 images of separate Y,
+    /// U and V planes. All other formats have a single plane.
+    pub fn num_planes(&self, desc: &Descriptor) -> usize {
+        match desc.pixfmt {
+            PixelFormat::Nv12 => 2,
+            PixelFormat::I420 | PixelFormat::Yv12 => 3,
+            _ => 1,
+        }
+    }
+
+    /// Returns a plane of an image in the given format
+    ///
+    /// Planes are numbered in Y, U, V order regardless of their order in memory, so plane 1 of a
+    /// YV12 image is its U plane. For single plane formats, plane 0 is the whole buffer. Returns
+    /// `None` if the index is out of range or the buffer is too small for the format.
+    pub fn plane(&self, desc: &Descriptor, index: usize) -> Option<&[u8]> {
+        let bytes = self.as_bytes();
+        let layout = match desc.pixfmt.planes(desc.width, desc.height) {
+            Some(layout) => layout,
+            None if index == 0 => return Some(bytes),
+            None => return None,
+        };
+        if bytes.len() < layout.len {
+            return None;
+        }
+
+        let y_len = layout.y_stride * desc.height as usize;
+        let c_len = layout.c_stride * (desc.height as usize).div_ceil(2);
+        let range = match (&desc.pixfmt, index) {
+            (_, 0) => layout.y_offset..layout.y_offset + y_len,
+            // the interleaved UV plane of NV12 starts at the first U sample
+            (_, 1) => layout.u_offset..layout.u_offset + c_len,
+            (PixelFormat::I420 | PixelFormat::Yv12, 2) => layout.v_offset..layout.v_offset + c_len,
+            _ => return None,
+        };
+
+        Some(&bytes[range])
+    }
+
+    /// Returns the samples of a 16-bit or packed 10/12-bit image
+    ///
+    /// 16-bit grayscale and depth images are stored in little endian byte order, the samples are
+    /// returned without copying any data on little endian hosts if the buffer is suitably
+    /// aligned. 10- and 12-bit grayscale images are expected to be packed as defined by MIPI
+    /// CSI-2 (four 10-bit samples in five bytes, two 12-bit samples in three bytes) and are
+    /// unpacked. The samples keep their original range, e.g. 0..=1023 for 10-bit images.
+    pub fn as_u16_samples(&self, desc: &Descriptor) -> Result<Cow<'_, [u16]>> {
+        let bytes = self.as_bytes();
+        let pixels = desc.width as usize * desc.height as usize;
+        let (samples_per_group, bytes_per_group) = match desc.pixfmt {
+            PixelFormat::Gray(16) | PixelFormat::Depth(16) => (1, 2),
+            PixelFormat::Gray(10) => (4, 5),
+            PixelFormat::Gray(12) => (2, 3),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::NotSupported,
+                    format!("cannot represent {} as 16-bit samples", desc.pixfmt),
+                ))
+            }
+        };
+
+        if pixels % samples_per_group != 0 {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "pixel count is not a multiple of the packing group size",
+            ));
+        }
+        let len = pixels / samples_per_group * bytes_per_group;
+        if bytes.len() < len {
+            return Err(Error::new(ErrorKind::Other, "buffer too small"));
+        }
+        let bytes = &bytes[..len];
+
+        let samples = match desc.pixfmt {
+            PixelFormat::Gray(10) => bytes
+                .chunks_exact(5)
+                .flat_map(|group| {
+                    // the fifth byte holds the two least significant bits of each sample
+                    let low = group[4] as u16;
+                    (0..4).map(move |i| (group[i] as u16) << 2 | (low >> (2 * i)) & 0x3)
+                })
+                .collect(),
+            PixelFormat::Gray(12) => bytes
+                .chunks_exact(3)
+                .flat_map(|group| {
+                    let low = group[2] as u16;
+                    [
+                        (group[0] as u16) << 4 | low & 0xf,
+                        (group[1] as u16) << 4 | low >> 4,
+                    ]
+                })
+                .collect(),
+            _ => {
+                if cfg!(target_endian = "little") {
+                    // Safe because any bit pattern is a valid u16.
+                    let (prefix, samples, _) = unsafe { bytes.align_to::<u16>() };
+                    if prefix.is_empty() {
+                        return Ok(Cow::Borrowed(samples));
+                    }
+                }
+
+                bytes
+                    .chunks_exact(2)
+                    .map(|sample| u16::from_le_bytes([sample[0], sample[1]]))
+                    .collect()
+            }
+        };
+
+        Ok(Cow::Owned(samples))
+    }
+
     /// Returns an instance that is guaranteed to own its data
     ///
     /// If the instance currently borrows the data, it is cloned and transferred. Otherwise, no
@@ -32,6 +279,7 @@ impl<'a> Buffer<'a> {
     pub fn own<'b>(self) -> Buffer<'b> {
         Buffer {
             inner: Cow::Owned(self.inner.into_owned()),
+            meta: self.meta,
         }
     }
 }
@@ -40,6 +288,7 @@ impl<'a> From<&'a [u8]> for Buffer<'a> {
     fn from(bytes: &'a [u8]) -> Self {
         Buffer {
             inner: Cow::Borrowed(bytes),
+            meta: Metadata::default(),
         }
     }
 }
@@ -48,6 +297,216 @@ impl<'a> From<Vec<u8>> for Buffer<'a> {
     fn from(bytes: Vec<u8>) -> Self {
         Buffer {
             inner: Cow::Owned(bytes),
+            meta: Metadata::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Simple image statistics, e.g. for auto exposure and white balance algorithms
+pub struct FrameStats {
+    /// Mean luma value in the range of 0..=255
+    pub mean_luma: f32,
+    /// Mean value of each channel in the order of the pixel format (e.g. Y, U, V)
+    pub channel_means: Vec<f32>,
+    /// Luma histogram, one bin per value
+    pub histogram: [u32; 256],
+}
+
+/// Computes image statistics directly on the raw buffer data
+///
+/// Only formats which carry the luma information in a separate plane or in a simple pattern are
+/// supported (8-bit grayscale, YUYV and the planar YUV 4:2:0 formats), so no full decode is
+/// required. The buffer data is expected to be tightly packed, i.e. without any row padding.
+pub fn stats(buf: &Buffer, desc: &Descriptor) -> Result<FrameStats> {
+    let bytes = buf.as_bytes();
+    let pixels = desc.width as usize * desc.height as usize;
+    let planes = desc.pixfmt.planes(desc.width, desc.height);
+    let len = match (&desc.pixfmt, planes) {
+        (PixelFormat::Gray(8), _) => pixels,
+        (PixelFormat::Yuyv, _) => pixels * 2,
+        (_, Some(planes)) => planes.len,
+        _ => {
+            return Err(Error::new(
+                ErrorKind::NotSupported,
+                format!("cannot compute statistics for {}", desc.pixfmt),
+            ))
+        }
+    };
+
+    if pixels == 0 || bytes.len() < len {
+        return Err(Error::new(ErrorKind::Other, "buffer too small"));
+    }
+
+    let mut histogram = [0u32; 256];
+    let (luma, chroma) = match desc.pixfmt {
+        PixelFormat::Yuyv => (
+            bytes[..len].iter().step_by(2),
+            Some((
+                bytes[1..len].iter().step_by(4),
+                bytes[3..len].iter().step_by(4),
+            )),
+        ),
+        _ => match planes {
+            Some(planes) => {
+                // NV12 interleaves the chroma samples in a single plane
+                let (step, c_len) = match desc.pixfmt {
+                    PixelFormat::Nv12 => (2, len - pixels),
+                    _ => (1, (len - pixels) / 2),
+                };
+                (
+                    bytes[..pixels].iter().step_by(1),
+                    Some((
+                        bytes[planes.u_offset..planes.u_offset + c_len]
+                            .iter()
+                            .step_by(step),
+                        bytes[planes.v_offset..planes.v_offset + c_len - (step - 1)]
+                            .iter()
+                            .step_by(step),
+                    )),
+                )
+            }
+            None => (bytes[..pixels].iter().step_by(1), None),
+        },
+    };
+
+    let mut sum = 0u64;
+    luma.for_each(|&y| {
+        sum += y as u64;
+        histogram[y as usize] += 1;
+    });
+    let mean_luma = sum as f32 / pixels as f32;
+
+    let mut channel_means = vec![mean_luma];
+    if let Some((u, v)) = chroma {
+        for samples in [u, v].iter_mut() {
+            let (sum, count) =
+                samples.fold((0u64, 0u64), |(sum, count), &x| (sum + x as u64, count + 1));
+            channel_means.push(sum as f32 / count.max(1) as f32);
+        }
+    }
+
+    Ok(FrameStats {
+        mean_luma,
+        channel_means,
+        histogram,
+    })
+}
+
+/// Converts an image into a normalized planar (CHW) RGB tensor, e.g. as input of a neural network
+///
+/// The samples are scaled to 0..=1 and normalized per channel as `(x - mean) / std`, then written
+/// to `out` as three consecutive planes (R, G, B) of `width * height` values each. Everything
+/// happens in a single pass over the image, so no intermediate buffers are allocated.
+///
+/// RGB and BGR images (with or without alpha), 8-bit grayscale images (replicated to all three
+/// channels), YUYV and the planar YUV 4:2:0 formats are supported. YUV images are converted
+/// using the full range BT.601 coefficients. Compressed frames must be decoded first (e.g. by
+/// `eye::colorconvert::Converter`). YUYV images must have an even width. The buffer data is
+/// expected to be tightly packed, i.e. without any row padding.
+pub fn to_chw_f32(
+    buf: &Buffer,
+    desc: &Descriptor,
+    mean: [f32; 3],
+    std: [f32; 3],
+    out: &mut [f32],
+) -> Result<()> {
+    let bytes = buf.as_bytes();
+    let (width, height) = (desc.width as usize, desc.height as usize);
+    let pixels = width * height;
+    let planes = desc.pixfmt.planes(desc.width, desc.height);
+    let len = match (&desc.pixfmt, planes) {
+        (PixelFormat::Gray(8), _) => pixels,
+        (PixelFormat::Yuyv, _) => pixels * 2,
+        (PixelFormat::Rgb(24) | PixelFormat::Bgr(24), _) => pixels * 3,
+        (PixelFormat::Rgb(32) | PixelFormat::Bgr(32), _) => pixels * 4,
+        (_, Some(planes)) => planes.len,
+        _ => {
+            return Err(Error::new(
+                ErrorKind::NotSupported,
+                format!("cannot convert {} to a tensor", desc.pixfmt),
+            ))
         }
+    };
+
+    if pixels == 0 || bytes.len() < len {
+        return Err(Error::new(ErrorKind::Other, "buffer too small"));
+    }
+    // YUYV stores the chroma samples per pair of pixels
+    if desc.pixfmt == PixelFormat::Yuyv && width % 2 != 0 {
+        return Err(Error::new(
+            ErrorKind::NotSupported,
+            "cannot convert YUYV frames with an odd width",
+        ));
     }
+    if out.len() < pixels * 3 {
+        return Err(Error::new(ErrorKind::Other, "output buffer too small"));
+    }
+
+    // Fold the scaling and the normalization into a single multiply-add per sample.
+    let scale = [0, 1, 2].map(|c| 1.0 / (255.0 * std[c]));
+    let offset = [0, 1, 2].map(|c| mean[c] / std[c]);
+    let (r_plane, rest) = out[..pixels * 3].split_at_mut(pixels);
+    let (g_plane, b_plane) = rest.split_at_mut(pixels);
+    let mut store = |i: usize, (r, g, b): (f32, f32, f32)| {
+        r_plane[i] = r * scale[0] - offset[0];
+        g_plane[i] = g * scale[1] - offset[1];
+        b_plane[i] = b * scale[2] - offset[2];
+    };
+
+    match (&desc.pixfmt, planes) {
+        (PixelFormat::Gray(8), _) => {
+            for (i, &y) in bytes[..len].iter().enumerate() {
+                let y = y as f32;
+                store(i, (y, y, y));
+            }
+        }
+        (PixelFormat::Yuyv, _) => {
+            for i in 0..pixels {
+                // two neighboring pixels share their chroma samples
+                let pair = (i & !1) * 2;
+                store(
+                    i,
+                    yuv_to_rgb(bytes[i * 2], bytes[pair + 1], bytes[pair + 3]),
+                );
+            }
+        }
+        (_, Some(planes)) => {
+            // NV12 interleaves the chroma samples in a single plane
+            let step = match desc.pixfmt {
+                PixelFormat::Nv12 => 2,
+                _ => 1,
+            };
+            for row in 0..height {
+                for col in 0..width {
+                    let y = bytes[planes.y_offset + row * planes.y_stride + col];
+                    let c = (row / 2) * planes.c_stride + (col / 2) * step;
+                    let (u, v) = (bytes[planes.u_offset + c], bytes[planes.v_offset + c]);
+                    store(row * width + col, yuv_to_rgb(y, u, v));
+                }
+            }
+        }
+        (pixfmt, None) => {
+            let bytes_per_pixel = len / pixels;
+            let (r, g, b) = match pixfmt {
+                PixelFormat::Bgr(_) => (2, 1, 0),
+                _ => (0, 1, 2),
+            };
+            for (i, px) in bytes[..len].chunks_exact(bytes_per_pixel).enumerate() {
+                store(i, (px[r] as f32, px[g] as f32, px[b] as f32));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts a full range BT.601 YUV sample to RGB in the range of 0..=255
+fn yuv_to_rgb(y: u8, u: u8, v: u8) -> (f32, f32, f32) {
+    let (y, u, v) = (y as f32, u as f32 - 128.0, v as f32 - 128.0);
+    (
+        (y + 1.402 * v).clamp(0.0, 255.0),
+        (y - 0.344_136 * u - 0.714_136 * v).clamp(0.0, 255.0),
+        (y + 1.772 * u).clamp(0.0, 255.0),
+    )
 }
@@ -1,5 +1,9 @@
+use alloc::{string::String, vec::Vec};
+
 use bitflags::bitflags;
 
+use crate::error::Error;
+
 #[derive(Debug, Clone)]
 /// Device control
 pub struct Descriptor {
@@ -44,7 +48,12 @@ pub enum Type {
     /// Bit field
     Bitmask,
     /// Menu containing an arbitrary number of items
-    Menu(Vec<MenuItem>),
+    Menu {
+        /// Selectable items
+        items: Vec<MenuItem>,
+        /// Position of the factory default item in `items`, if known
+        default: Option<usize>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -56,6 +65,60 @@ pub enum MenuItem {
     Number(f64),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Platform independent control identifier
+///
+/// Control IDs are backend specific, use [`Device::control_id`](crate::traits::Device::control_id)
+/// to resolve the native ID of a control, e.g.:
+///
+/// ```no_run
+/// # use eye_hal::control::{ControlId, State};
+/// # use eye_hal::traits::Device;
+/// # fn example<'a, D: Device<'a>>(dev: &mut D) -> eye_hal::Result<()> {
+/// let id = dev.control_id(ControlId::Brightness)?;
+/// dev.set_control(id, &State::Number(128.0))?;
+/// # Ok(())
+/// # }
+/// ```
+pub enum ControlId {
+    Brightness,
+    Contrast,
+    Saturation,
+    Hue,
+    Gamma,
+    Sharpness,
+    BacklightCompensation,
+    /// Exposure time
+    Exposure,
+    /// Automatic exposure mode
+    AutoExposure,
+    Gain,
+    /// White balance color temperature
+    WhiteBalance,
+    AutoWhiteBalance,
+    Focus,
+    AutoFocus,
+    Zoom,
+    Pan,
+    Tilt,
+    /// Native control ID, passed through as is
+    Raw(u32),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Which value of a control to read
+pub enum Which {
+    /// The current value
+    #[default]
+    Current,
+    /// The factory default value
+    Default,
+    /// The minimum value
+    Min,
+    /// The maximum value
+    Max,
+}
+
 bitflags! {
     /// Control state flags
     pub struct Flags: u32 {
@@ -68,7 +131,32 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Automatic algorithms which use a region of interest
+    ///
+    /// The values match the `bmAutoControls` field of the UVC region of interest control.
+    pub struct MeteringMode: u16 {
+        /// Auto exposure
+        const EXPOSURE              = 0x0001;
+        /// Auto iris
+        const IRIS                  = 0x0002;
+        /// Auto white balance
+        const WHITE_BALANCE         = 0x0004;
+        /// Auto focus
+        const FOCUS                 = 0x0008;
+        /// Face detection
+        const FACE_DETECT           = 0x0010;
+        /// Detect and track the object in the region
+        const DETECT_AND_TRACK      = 0x0020;
+        /// Image stabilization
+        const IMAGE_STABILIZATION   = 0x0040;
+        /// Prefer image quality over other aspects (e.g. frame rate)
+        const HIGHER_QUALITY        = 0x0080;
+    }
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Device control state
 pub enum State {
     /* Stateless controls */
@@ -79,3 +167,108 @@ pub enum State {
     Boolean(bool),
     Number(f64),
 }
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Saved control values of a device, e.g. to restore a settings profile later on
+pub struct Snapshot {
+    /// Control values in the order they were read
+    pub values: Vec<SnapshotEntry>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A single saved control value
+pub struct SnapshotEntry {
+    /// Control identifier
+    pub id: u32,
+    /// Control name, for informational purposes only
+    pub name: String,
+    /// Saved value
+    pub value: State,
+}
+
+#[derive(Debug)]
+/// A saved control value which could not be applied
+pub struct Mismatch {
+    /// Control identifier
+    pub id: u32,
+    /// Control name
+    pub name: String,
+    /// Reason, e.g. `ErrorKind::NotSupported` if the device does not have the control
+    pub error: Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Mains frequency the device compensates for to avoid flicker
+pub enum PowerLineFrequency {
+    /// No flicker compensation
+    Disabled,
+    /// 50 Hz mains (e.g. Europe, most of Asia)
+    Hz50,
+    /// 60 Hz mains (e.g. North America)
+    Hz60,
+    /// Let the device detect the frequency
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How the lens is focused
+pub enum FocusMode {
+    /// The lens stays at the position set by the application
+    Manual,
+    /// Focus once, then keep the lens at the found position
+    Auto,
+    /// Keep adjusting the focus while streaming
+    Continuous,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Range of distances the automatic focus searches
+pub enum FocusRange {
+    /// Let the device choose the range
+    Auto,
+    /// The regular range of the lens, excluding macro distances
+    Normal,
+    /// Close distances only
+    Macro,
+    /// Focus at infinity
+    Infinity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Request types of UVC extension unit controls as defined by the UVC specification
+pub enum XuQuery {
+    /// Write the current value
+    SetCur,
+    /// Read the current value
+    GetCur,
+    /// Read the minimum value
+    GetMin,
+    /// Read the maximum value
+    GetMax,
+    /// Read the resolution (step size)
+    GetRes,
+    /// Read the size of the control in bytes (as little endian u16)
+    GetLen,
+    /// Read the control capabilities (a bitmask)
+    GetInfo,
+    /// Read the default value
+    GetDef,
+}
+
+impl XuQuery {
+    /// Returns the request code as defined by the UVC specification
+    pub fn code(&self) -> u8 {
+        match self {
+            XuQuery::SetCur => 0x01,
+            XuQuery::GetCur => 0x81,
+            XuQuery::GetMin => 0x82,
+            XuQuery::GetMax => 0x83,
+            XuQuery::GetRes => 0x84,
+            XuQuery::GetLen => 0x85,
+            XuQuery::GetInfo => 0x86,
+            XuQuery::GetDef => 0x87,
+        }
+    }
+}
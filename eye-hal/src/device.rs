@@ -1,3 +1,10 @@
+use alloc::{string::String, vec::Vec};
+
+use bitflags::bitflags;
+
+use crate::geometry::Rect;
+use crate::stream;
+
 #[derive(Clone, Debug)]
 /// Device description
 pub struct Description {
@@ -5,4 +12,211 @@ pub struct Description {
     pub uri: String,
     /// Human-readable product name
     pub product: String,
+    /// Whether the device can only be opened by one process (or handle) at a time
+    ///
+    /// Even devices which can be opened multiple times usually only run one stream at a time, but
+    /// other handles may still read and write controls.
+    pub exclusive: bool,
+    /// Physical location of the device, e.g. the sysfs path on Linux
+    ///
+    /// Unlike the URI, which may change when devices are plugged in a different order, the path
+    /// is tied to the port the device is connected to. This is what udev rules or container
+    /// device mappings should refer to.
+    pub bus_path: Option<String>,
+}
+
+impl Info for Description {
+    fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    fn product(&self) -> String {
+        self.product.clone()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        // only capture devices are described
+        Capabilities::VIDEO_CAPTURE | Capabilities::STREAMING
+    }
+
+    fn bus_path(&self) -> Option<String> {
+        self.bus_path.clone()
+    }
+}
+
+bitflags! {
+    /// Device capabilities
+    pub struct Capabilities: u32 {
+        /// No capabilities
+        const NONE                  = 0x000;
+        /// The device captures video
+        const VIDEO_CAPTURE         = 0x001;
+        /// Frames can be streamed through buffers
+        const STREAMING             = 0x002;
+        /// Frames can be read like from a file
+        const READ_WRITE            = 0x004;
+        /// The device captures frame metadata
+        const META_CAPTURE          = 0x008;
+        /// The device captures video through the multi-planar API
+        const VIDEO_CAPTURE_MPLANE  = 0x010;
+        /// The device supports the extended pixel format fields (e.g. colorspace flags)
+        const EXT_PIX_FORMAT        = 0x020;
+        /// The device has audio inputs
+        const AUDIO                 = 0x040;
+    }
+}
+
+/// Properties of a device which is matched against a predicate
+///
+/// See [`Context::find_devices`](crate::traits::Context::find_devices). Contexts query the
+/// properties on demand where possible, so check the cheap ones (e.g. the URI) first to avoid
+/// opening devices which are rejected anyways.
+pub trait Info {
+    /// Returns the unique resource identifier
+    fn uri(&self) -> &str;
+
+    /// Returns the human-readable product name
+    fn product(&self) -> String;
+
+    /// Returns the capabilities of the device
+    fn capabilities(&self) -> Capabilities;
+
+    /// Returns the physical location of the device, see [`Description::bus_path`]
+    ///
+    /// By default, the location is unknown.
+    fn bus_path(&self) -> Option<String> {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+/// Cropping capabilities of a device
+pub struct CropCaps {
+    /// Area the device can capture from, e.g. the full sensor area
+    pub bounds: Rect,
+    /// Area captured by default
+    pub default: Rect,
+    /// Pixel aspect ratio (y / x) as (numerator, denominator) when no scaling is applied
+    pub pixel_aspect: (u32, u32),
+}
+
+#[derive(Clone, Debug)]
+/// Audio input of a capture device
+///
+/// Capture cards (e.g. for HDMI) usually deliver the embedded audio through a separate sound
+/// card rather than through the video device.
+pub struct AudioInput {
+    /// Index of the input as reported by the driver
+    pub index: u32,
+    /// Human-readable name
+    pub name: String,
+    /// Whether the input carries stereo audio
+    pub stereo: bool,
+    /// ALSA device of the sound card which belongs to the capture device, e.g. `hw:CARD=HDMI`
+    ///
+    /// The name refers to the card by its ID rather than by its number, so it stays valid when
+    /// the cards are enumerated in a different order.
+    pub alsa_device: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// USB bus a device is connected to
+pub struct UsbBus {
+    /// Bus number
+    pub number: u32,
+    /// Negotiated link speed in Mbit/s (e.g. 480 for USB 2.0 high speed), if known
+    pub speed: Option<u32>,
+}
+
+impl UsbBus {
+    /// Returns the bandwidth available for isochronous video transfers in bytes per second
+    ///
+    /// Protocol overhead and the share reserved for other transfers leave about two thirds of the
+    /// link speed for video, e.g. 40 MB/s on USB 2.0. Buses of unknown speed are assumed to run at
+    /// USB 2.0 high speed.
+    pub fn practical_limit(&self) -> u64 {
+        practical_limit(self.speed)
+    }
+}
+
+fn practical_limit(speed: Option<u32>) -> u64 {
+    speed.unwrap_or(480) as u64 * 1_000_000 / 8 * 2 / 3
+}
+
+#[derive(Clone, Debug)]
+/// Estimated bandwidth usage of the streams on one bus
+pub struct BusUsage {
+    /// The bus, `None` for streams whose bus is unknown
+    pub bus: Option<UsbBus>,
+    /// Indices of the streams on this bus
+    pub streams: Vec<usize>,
+    /// Indices of the streams whose bandwidth cannot be estimated, e.g. because they are
+    /// compressed
+    pub unknown: Vec<usize>,
+    /// Combined bandwidth of the estimated streams in bytes per second
+    pub bytes_per_second: u64,
+    /// Practical bandwidth limit of the bus in bytes per second
+    pub limit: u64,
+}
+
+impl BusUsage {
+    /// Returns whether the streams likely exceed the bus bandwidth
+    pub fn oversubscribed(&self) -> bool {
+        self.bytes_per_second > self.limit
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// Estimated bandwidth usage of a set of streams, grouped by bus
+pub struct BandwidthReport {
+    /// Usage per bus
+    pub buses: Vec<BusUsage>,
+}
+
+impl BandwidthReport {
+    /// Estimates the bandwidth of streams on the given buses
+    ///
+    /// Streams whose bus is unknown are assumed to share a single USB 2.0 bus, which is the
+    /// pessimistic choice for cameras connected to the same machine.
+    pub fn new(streams: &[(Option<UsbBus>, &stream::Descriptor)]) -> Self {
+        let mut report = BandwidthReport::default();
+        for (index, (bus, desc)) in streams.iter().enumerate() {
+            // devices on the same bus may have negotiated different speeds
+            let number = bus.map(|bus| bus.number);
+            let pos = match report
+                .buses
+                .iter()
+                .position(|usage| usage.bus.map(|bus| bus.number) == number)
+            {
+                Some(pos) => pos,
+                None => {
+                    report.buses.push(BusUsage {
+                        bus: *bus,
+                        streams: Vec::new(),
+                        unknown: Vec::new(),
+                        bytes_per_second: 0,
+                        limit: practical_limit(bus.and_then(|bus| bus.speed)),
+                    });
+                    report.buses.len() - 1
+                }
+            };
+
+            let usage = &mut report.buses[pos];
+            usage.streams.push(index);
+            let interval = desc.interval.as_nanos();
+            match desc.frame_bytes() {
+                Some(bytes) if interval > 0 => {
+                    usage.bytes_per_second += (bytes as u128 * 1_000_000_000 / interval) as u64;
+                }
+                _ => usage.unknown.push(index),
+            }
+        }
+
+        report
+    }
+
+    /// Returns whether any bus is likely oversubscribed
+    pub fn oversubscribed(&self) -> bool {
+        self.buses.iter().any(BusUsage::oversubscribed)
+    }
 }
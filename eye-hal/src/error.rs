@@ -1,4 +1,11 @@
-use std::{error, fmt, io, result};
+use alloc::boxed::Box;
+#[cfg(not(feature = "std"))]
+use alloc::string::{String, ToString};
+use core::{fmt, result};
+#[cfg(feature = "std")]
+use std::{error, io};
+
+use crate::stream::Descriptor;
 
 //
 // Modeled after std::io::error: https://doc.rust-lang.org/src/std/io/error.rs.html.
@@ -30,13 +37,42 @@ impl fmt::Debug for Repr {
 #[derive(Debug)]
 struct Custom {
     kind: ErrorKind,
+    #[cfg(feature = "std")]
     error: Box<dyn error::Error + Send + Sync>,
+    // There is no error trait without std, so we can only keep the message around.
+    #[cfg(not(feature = "std"))]
+    error: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ErrorKind {
     /// This operation is not supported.
     NotSupported,
+    /// The video source changed (e.g. its resolution), the stream must be renegotiated.
+    SourceChanged,
+    /// The device is in use by another process.
+    DeviceBusy,
+    /// Insufficient permissions to access the device.
+    PermissionDenied,
+    /// The frame was not captured completely and its data is unusable.
+    CorruptFrame,
+    /// No device is available.
+    NoDevices,
+    /// The driver reported more data than the buffer can hold.
+    InvalidBufferLength,
+    /// No frame arrived within the configured time.
+    Timeout,
+    /// The device disappeared, e.g. because it was unplugged.
+    ///
+    /// Unlike the end of a stream, this is permanent: the device must be opened again once it
+    /// is back.
+    DeviceLost,
+    /// The device does not offer the requested frame interval for the format.
+    UnsupportedInterval,
+    /// The driver substituted a different frame size or pixel format for the requested one.
+    ///
+    /// The error carries a [`FormatMismatch`] holding both, see [`Error::format_mismatch`].
+    FormatNotSupportedAtSize,
     /// Any other error not part of this list.
     Other,
 }
@@ -45,12 +81,23 @@ impl fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ErrorKind::NotSupported => write!(f, "not supported"),
+            ErrorKind::SourceChanged => write!(f, "source changed"),
+            ErrorKind::DeviceBusy => write!(f, "device busy"),
+            ErrorKind::PermissionDenied => write!(f, "permission denied"),
+            ErrorKind::CorruptFrame => write!(f, "corrupt frame"),
+            ErrorKind::NoDevices => write!(f, "no devices"),
+            ErrorKind::InvalidBufferLength => write!(f, "invalid buffer length"),
+            ErrorKind::Timeout => write!(f, "timed out"),
+            ErrorKind::DeviceLost => write!(f, "device lost"),
+            ErrorKind::UnsupportedInterval => write!(f, "unsupported interval"),
+            ErrorKind::FormatNotSupportedAtSize => write!(f, "format not supported at size"),
             ErrorKind::Other => write!(f, "other"),
         }
     }
 }
 
 impl Error {
+    #[cfg(feature = "std")]
     pub fn new<E>(kind: ErrorKind, error: E) -> Self
     where
         E: Into<Box<dyn error::Error + Send + Sync>>,
@@ -62,6 +109,80 @@ impl Error {
             })),
         }
     }
+
+    #[cfg(not(feature = "std"))]
+    pub fn new<E>(kind: ErrorKind, error: E) -> Self
+    where
+        E: Into<String>,
+    {
+        Error {
+            repr: Repr::Custom(Box::new(Custom {
+                kind,
+                error: error.into(),
+            })),
+        }
+    }
+
+    /// Returns the corresponding `ErrorKind` for this error.
+    pub fn kind(&self) -> ErrorKind {
+        match &self.repr {
+            Repr::Simple(kind) => *kind,
+            Repr::Custom(c) => c.kind,
+        }
+    }
+
+    /// Returns the inner error wrapped by this error, if any.
+    #[cfg(feature = "std")]
+    pub fn get_ref(&self) -> Option<&(dyn error::Error + Send + Sync + 'static)> {
+        match &self.repr {
+            Repr::Simple(_) => None,
+            Repr::Custom(c) => Some(&*c.error),
+        }
+    }
+
+    /// Returns the requested and the negotiated format if the driver substituted the format.
+    #[cfg(feature = "std")]
+    pub fn format_mismatch(&self) -> Option<&FormatMismatch> {
+        self.get_ref()?.downcast_ref()
+    }
+}
+
+/// A format the driver replaced by a different one during negotiation
+///
+/// Drivers usually accept any format and adjust it to the nearest one they support instead of
+/// rejecting it.
+#[derive(Clone, Debug)]
+pub struct FormatMismatch {
+    /// Stream which was requested
+    pub requested: Descriptor,
+    /// Stream which the driver chose instead
+    pub got: Descriptor,
+}
+
+impl fmt::Display for FormatMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "requested {}x{} {}, but the driver chose {}x{} {}",
+            self.requested.width,
+            self.requested.height,
+            self.requested.pixfmt,
+            self.got.width,
+            self.got.height,
+            self.got.pixfmt
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for FormatMismatch {}
+
+impl From<FormatMismatch> for Error {
+    fn from(mismatch: FormatMismatch) -> Self {
+        #[cfg(not(feature = "std"))]
+        let mismatch = mismatch.to_string();
+        Error::new(ErrorKind::FormatNotSupportedAtSize, mismatch)
+    }
 }
 
 impl From<ErrorKind> for Error {
@@ -72,11 +193,22 @@ impl From<ErrorKind> for Error {
     }
 }
 
+#[cfg(feature = "std")]
 impl From<io::Error> for Error {
     fn from(error: io::Error) -> Self {
+        let kind = match error.kind() {
+            io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+            io::ErrorKind::TimedOut => ErrorKind::Timeout,
+            #[cfg(target_os = "linux")]
+            _ if error.raw_os_error() == Some(libc::EBUSY) => ErrorKind::DeviceBusy,
+            #[cfg(target_os = "linux")]
+            _ if error.raw_os_error() == Some(libc::ENODEV) => ErrorKind::DeviceLost,
+            _ => ErrorKind::Other,
+        };
+
         Error {
             repr: Repr::Custom(Box::new(Custom {
-                kind: ErrorKind::Other,
+                kind,
                 error: error.into(),
             })),
         }
@@ -87,11 +219,12 @@ impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match &self.repr {
             Repr::Simple(kind) => write!(fmt, "{}", kind),
-            Repr::Custom(ref c) => c.error.fmt(fmt),
+            Repr::Custom(ref c) => fmt::Display::fmt(&c.error, fmt),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl error::Error for Error {
     fn cause(&self) -> Option<&dyn std::error::Error> {
         None
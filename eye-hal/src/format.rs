@@ -1,8 +1,10 @@
-use std::cmp::{Eq, PartialEq};
-use std::fmt;
-use std::hash::Hash;
+use alloc::string::String;
+use core::cmp::{Eq, PartialEq};
+use core::fmt;
+use core::hash::Hash;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Pixel format type used to describe image pixels.
 ///
 /// Arbitrary formats can be wrapped in the Custom variant.
@@ -10,6 +12,8 @@ use std::hash::Hash;
 pub enum PixelFormat {
     /// Special type for application defined formats
     Custom(String),
+    /// Four character code of a format unknown to this crate, passed through to the driver as is
+    Fourcc([u8; 4]),
 
     /// Z buffers
     Depth(u32),
@@ -21,6 +25,15 @@ pub enum PixelFormat {
     /// Red, Green, Blue
     Rgb(u32),
 
+    /// Packed YUV 4:2:2 (Y0, U, Y1, V)
+    Yuyv,
+    /// Planar YUV 4:2:0 with one Y plane and one interleaved UV plane
+    Nv12,
+    /// Planar YUV 4:2:0 with separate Y, U and V planes (also known as YU12)
+    I420,
+    /// Planar YUV 4:2:0 with separate Y, V and U planes
+    Yv12,
+
     /// JPEG compression
     Jpeg,
 }
@@ -30,21 +43,122 @@ impl PixelFormat {
     pub fn bits(&self) -> Option<u32> {
         match self {
             // Custom
-            PixelFormat::Custom(_) => None,
+            PixelFormat::Custom(_) | PixelFormat::Fourcc(_) => None,
             // Uncompressed
             PixelFormat::Depth(bits) => Some(*bits),
             PixelFormat::Gray(bits) => Some(*bits),
             PixelFormat::Bgr(bits) => Some(*bits),
             PixelFormat::Rgb(bits) => Some(*bits),
+            PixelFormat::Yuyv => Some(16),
+            PixelFormat::Nv12 | PixelFormat::I420 | PixelFormat::Yv12 => Some(12),
             // Compressed
             PixelFormat::Jpeg => None,
         }
     }
+
+    /// Returns true if the pixel data is compressed, i.e. needs to be decoded
+    pub fn is_compressed(&self) -> bool {
+        matches!(self, PixelFormat::Jpeg)
+    }
+
+    /// Returns true if the image is stored in multiple planes
+    pub fn is_planar(&self) -> bool {
+        matches!(
+            self,
+            PixelFormat::Nv12 | PixelFormat::I420 | PixelFormat::Yv12
+        )
+    }
+
+    /// Returns the number of color channels (e.g. 3 for RGB or YUV, 4 for RGBA)
+    ///
+    /// Returns `None` for custom and compressed formats.
+    pub fn channel_count(&self) -> Option<u32> {
+        match self {
+            PixelFormat::Custom(_) | PixelFormat::Fourcc(_) | PixelFormat::Jpeg => None,
+            PixelFormat::Depth(_) | PixelFormat::Gray(_) => Some(1),
+            // 32-bit RGB carries an alpha channel
+            PixelFormat::Bgr(32) | PixelFormat::Rgb(32) => Some(4),
+            PixelFormat::Bgr(_) | PixelFormat::Rgb(_) => Some(3),
+            PixelFormat::Yuyv | PixelFormat::Nv12 | PixelFormat::I420 | PixelFormat::Yv12 => {
+                Some(3)
+            }
+        }
+    }
+
+    /// Returns the plane layout of a planar YUV 4:2:0 image
+    ///
+    /// The planes are expected to be tightly packed, i.e. without any row padding. For odd
+    /// dimensions, the chroma planes are rounded up.
+    ///
+    /// Returns `None` for all other formats.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use eye_hal::format::PixelFormat;
+    /// let planes = PixelFormat::I420.planes(640, 480).unwrap();
+    /// assert_eq!(planes.u_offset, 640 * 480);
+    /// assert_eq!(planes.v_offset, 640 * 480 + 320 * 240);
+    /// ```
+    pub fn planes(&self, width: u32, height: u32) -> Option<PlaneLayout> {
+        let (width, height) = (width as usize, height as usize);
+        let y_size = width * height;
+        let c_width = width.div_ceil(2);
+        let c_size = c_width * height.div_ceil(2);
+
+        let (u_offset, v_offset, c_stride) = match self {
+            // U and V samples alternate in a single plane
+            PixelFormat::Nv12 => (y_size, y_size + 1, c_width * 2),
+            PixelFormat::I420 => (y_size, y_size + c_size, c_width),
+            PixelFormat::Yv12 => (y_size + c_size, y_size, c_width),
+            _ => return None,
+        };
+
+        Some(PlaneLayout {
+            y_offset: 0,
+            u_offset,
+            v_offset,
+            y_stride: width,
+            c_stride,
+            len: y_size + c_size * 2,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Byte offsets and strides of the planes of a YUV image
+pub struct PlaneLayout {
+    /// Offset of the Y plane
+    pub y_offset: usize,
+    /// Offset of the first U sample
+    pub u_offset: usize,
+    /// Offset of the first V sample
+    pub v_offset: usize,
+    /// Length of a row in the Y plane in bytes
+    pub y_stride: usize,
+    /// Length of a row in the chroma plane(s) in bytes
+    pub c_stride: usize,
+    /// Length of the whole image in bytes
+    pub len: usize,
 }
 
 impl fmt::Display for PixelFormat {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            PixelFormat::Fourcc(repr) => {
+                write!(f, "Fourcc(")?;
+                for &byte in repr {
+                    // four character codes are usually, but not necessarily printable
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        write!(f, "{}", byte as char)?;
+                    } else {
+                        write!(f, "\\x{:02x}", byte)?;
+                    }
+                }
+                write!(f, ")")
+            }
+            _ => write!(f, "{:?}", self),
+        }
     }
 }
 
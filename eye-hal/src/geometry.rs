@@ -0,0 +1,156 @@
+use core::time::Duration;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Rectangular image area
+pub struct Rect {
+    /// Horizontal offset of the top left corner in pixels
+    pub left: i32,
+    /// Vertical offset of the top left corner in pixels
+    pub top: i32,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+}
+
+impl Rect {
+    /// Returns a new rectangle
+    pub fn new(left: i32, top: i32, width: u32, height: u32) -> Self {
+        Rect {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the size of the rectangle
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    /// Returns the rectangle moved by the given distance
+    pub fn offset(&self, dx: i32, dy: i32) -> Self {
+        Rect {
+            left: self.left.saturating_add(dx),
+            top: self.top.saturating_add(dy),
+            ..*self
+        }
+    }
+
+    /// Returns the rectangle moved (and shrunk, if it is larger) to lie within `bounds`
+    pub fn clamp_to(&self, bounds: &Rect) -> Self {
+        let width = self.width.min(bounds.width);
+        let height = self.height.min(bounds.height);
+        let left = self
+            .left
+            .min(bounds.left.saturating_add((bounds.width - width) as i32))
+            .max(bounds.left);
+        let top = self
+            .top
+            .min(bounds.top.saturating_add((bounds.height - height) as i32))
+            .max(bounds.top);
+
+        Rect {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// Image dimensions
+pub struct Size {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+}
+
+impl Size {
+    /// Returns a new size
+    pub fn new(width: u32, height: u32) -> Self {
+        Size { width, height }
+    }
+
+    /// Returns the number of pixels
+    pub fn area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Ratio of two integers, e.g. a frame interval in seconds
+///
+/// Platforms usually describe frame intervals as fractions. Rates like the NTSC one are not an
+/// integer number of frames per second, so e.g. an interval of 1001/30000s needs to be passed on
+/// as such rather than as a rounded number.
+pub struct Fraction {
+    /// Numerator
+    pub numerator: u32,
+    /// Denominator
+    pub denominator: u32,
+}
+
+impl Fraction {
+    /// Returns a new fraction
+    pub fn new(numerator: u32, denominator: u32) -> Self {
+        Fraction {
+            numerator,
+            denominator,
+        }
+    }
+
+    /// Returns the value of the fraction, infinity if the denominator is zero
+    pub fn as_f64(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+impl From<Duration> for Fraction {
+    /// Returns the simplest fraction of seconds matching a duration
+    fn from(duration: Duration) -> Self {
+        let nanos = duration.as_nanos();
+        if nanos == 0 {
+            return Fraction::new(0, 1);
+        }
+
+        // Every fraction within half a nanosecond rounds to the duration, pick the one with the
+        // smallest denominator. Integer arithmetic keeps this exact, e.g. 59.94 fps yields
+        // 1001/60000, which its convergents would miss.
+        let (p, q) = simplest_fraction(2 * nanos - 1, 2_000_000_000, 2 * nanos + 1, 2_000_000_000);
+        if p > u32::MAX as u128 || q > u32::MAX as u128 {
+            // the duration is too long to be represented, which no frame interval is anyways
+            return Fraction::new(u32::MAX, 1);
+        }
+        Fraction::new(p as u32, q as u32)
+    }
+}
+
+/// Returns the fraction with the smallest denominator in the interval [a / b, c / d]
+fn simplest_fraction(a: u128, b: u128, c: u128, d: u128) -> (u128, u128) {
+    let int = a / b;
+    if int * b == a {
+        return (int, 1);
+    }
+    if (int + 1) * d <= c {
+        return (int + 1, 1);
+    }
+
+    // Both bounds have the same integer part, continue with the reciprocals of the remainders.
+    let (p, q) = simplest_fraction(d, c - int * d, b, a - int * b);
+    (int * p + q, p)
+}
+
+impl From<Fraction> for Duration {
+    /// Returns the duration of a fraction of seconds, rounded to the nearest nanosecond
+    ///
+    /// Converting the duration back into a fraction recovers the original one.
+    fn from(fraction: Fraction) -> Self {
+        let denominator = fraction.denominator.max(1) as u128;
+        let nanos = (fraction.numerator as u128 * 1_000_000_000 + denominator / 2) / denominator;
+        Duration::from_nanos(nanos as u64)
+    }
+}
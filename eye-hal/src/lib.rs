@@ -31,20 +31,16 @@
 //!
 //! ```no_run
 //! use eye_hal::PlatformContext;
+//! use eye_hal::stream::DeviceStreamSettings;
 //! use eye_hal::traits::{Context, Device, Stream};
 //!
 //! // Create a context
 //! let ctx = PlatformContext::default();
 //!
-//! // Query for available devices.
-//! let devices = ctx.devices().expect("Failed to query devices");
-//! if devices.is_empty() {
-//!     panic!("No devices available");
-//! }
-//!
 //! // First, we need a capture device to read images from. For this example, let's just choose
 //! // whatever device is first in the list.
-//! let dev = ctx.open_device(&devices[0]).expect("Failed to open video device");
+//! let device = ctx.first_device().expect("Failed to find a device");
+//! let dev = ctx.open_device(&device.uri).expect("Failed to open video device");
 //!
 //! // Query for available streams and just choose the first one.
 //! let streams = dev.streams().expect("Failed to query streams");
@@ -54,7 +50,9 @@
 //! // Since we want to capture images, we need to access the native image stream of the device.
 //! // The backend will internally select a suitable implementation for the platform stream. On
 //! // Linux for example, most devices support memory-mapped buffers.
-//! let mut stream = dev.start_stream(&stream_desc).expect("Failed to setup capture stream");
+//! let mut stream = dev
+//!     .start_stream(DeviceStreamSettings::new(&stream_desc))
+//!     .expect("Failed to setup capture stream");
 //!
 //! // Now we are all set to start capturing some frames!
 //! let _buf = stream
@@ -66,15 +64,34 @@
 //!
 //! Have a look at the examples to learn more about device and stream management.
 
+//!
+//! # no_std
+//!
+//! The data types (buffers, controls, formats, stream descriptors, ...) only depend on `core` and
+//! `alloc`. Disable the default `std` feature to share them with `no_std` code, e.g. firmware
+//! which forwards frames to a host. The traits and the platform backends require `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+#[macro_use]
+mod trace;
+
 pub mod buffer;
 pub mod control;
 pub mod device;
 pub mod error;
 pub mod format;
+pub mod geometry;
 pub mod stream;
+#[cfg(feature = "std")]
 pub mod traits;
 
+#[cfg(feature = "std")]
 pub mod platform;
 
 pub use error::{Error, ErrorKind, Result};
+#[cfg(feature = "std")]
 pub use platform::Context as PlatformContext;
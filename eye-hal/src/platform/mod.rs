@@ -3,19 +3,29 @@
 //! Multiple backends can be implemented for a given platform.
 
 use std::array;
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+use std::time::{Duration, Instant};
 
 use crate::buffer::Buffer;
 use crate::control;
 use crate::device;
-use crate::error::Result;
+use crate::error::{Error, ErrorKind, Result};
+use crate::format::{ImageFormat, PixelFormat};
+use crate::geometry::Rect;
 use crate::stream;
 use crate::traits::{Context as ContextTrait, Device as DeviceTrait, Stream as StreamTrait};
 
 #[cfg(target_os = "linux")]
-pub(crate) mod v4l2;
+pub mod v4l2;
 
 #[cfg(feature = "plat-uvc")]
-pub(crate) mod uvc;
+pub mod uvc;
+
+pub mod rawfile;
+
+#[cfg(feature = "testutil")]
+pub mod scripted;
 
 /// Platform context
 ///
@@ -38,18 +48,107 @@ impl<'a> Context<'a> {
     pub fn all() -> impl Iterator<Item = Context<'a>> {
         array::IntoIter::new([
             #[cfg(target_os = "linux")]
-            Context::V4l2(v4l2::context::Context {}),
+            Context::V4l2(v4l2::context::Context::new()),
             #[cfg(feature = "plat-uvc")]
             Context::Uvc(uvc::context::Context {}),
         ])
     }
+
+    /// Returns a V4L2 context whose devices bypass libv4l format emulation
+    ///
+    /// libv4l may offer additional formats which it converts in software, e.g. RGB for cameras
+    /// that only produce MJPEG or Bayer data. Devices opened through this context only report the
+    /// formats natively supported by the hardware in their
+    /// [`streams`](DeviceTrait::streams), so no stream descriptor is
+    /// [`emulated`](stream::Descriptor::emulated) and frames are delivered as captured. Requesting
+    /// a format which was only available through emulation fails.
+    ///
+    /// Use [`Self::default`] to keep the emulated formats.
+    #[cfg(target_os = "linux")]
+    pub fn v4l2_native_only() -> Self {
+        Context::V4l2(v4l2::context::Context::native_only())
+    }
+
+    /// Returns a context which talks to UVC cameras directly over USB through libusb
+    ///
+    /// Unlike the OS backend, this works the same on every platform and exposes the UVC controls
+    /// and formats as the camera reports them. It also works without access to the V4L2 nodes
+    /// (e.g. on headless systems), as long as the process may access the USB device. The camera
+    /// is claimed exclusively while it is open, see [`device::Description::exclusive`].
+    #[cfg(feature = "plat-uvc")]
+    pub fn uvc() -> Self {
+        Context::Uvc(uvc::context::Context {})
+    }
+
+    /// Selects the memory backing the stream buffers of all devices opened through the context
+    ///
+    /// This centralizes the buffer strategy for applications which know the best one for their
+    /// platform. Individual streams may still select a different one through
+    /// [`DeviceStreamSettings::with_memory`](stream::DeviceStreamSettings::with_memory).
+    /// Only the V4L2 backend lets the application choose, the other backends manage their
+    /// buffers themselves and ignore this.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use eye_hal::platform::Context;
+    /// use eye_hal::stream::MemoryType;
+    ///
+    /// let ctx = Context::default().with_memory(MemoryType::UserPtr);
+    /// ```
+    pub fn with_memory(self, memory: stream::MemoryType) -> Self {
+        match self {
+            #[cfg(target_os = "linux")]
+            Self::V4l2(ctx) => Self::V4l2(ctx.with_memory(memory)),
+            ctx => {
+                let _ = memory;
+                ctx
+            }
+        }
+    }
+
+    /// Returns a device for an already opened file descriptor
+    ///
+    /// Only the V4L2 backend supports this, all other contexts return
+    /// [`ErrorKind::NotSupported`].
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `fd` is an open file descriptor referring to a device of the
+    /// context's backend. Ownership of the descriptor is transferred to the returned device.
+    #[cfg(unix)]
+    pub unsafe fn open_device_from_raw_fd<'b>(&self, fd: RawFd) -> Result<Device<'b>> {
+        match self {
+            #[cfg(target_os = "linux")]
+            Self::V4l2(ctx) => ctx.open_device_from_raw_fd(fd),
+            _ => {
+                let _ = fd;
+                Err(Error::from(ErrorKind::NotSupported))
+            }
+        }
+    }
+
+    /// Opens a device to inspect its controls and streams without capturing from it
+    ///
+    /// Only the V4L2 backend supports this, all other contexts return
+    /// [`ErrorKind::NotSupported`].
+    pub fn open_device_read_only<'b>(&self, uri: &str) -> Result<Device<'b>> {
+        match self {
+            #[cfg(target_os = "linux")]
+            Self::V4l2(ctx) => ctx.open_device_read_only(uri),
+            _ => {
+                let _ = uri;
+                Err(Error::from(ErrorKind::NotSupported))
+            }
+        }
+    }
 }
 
 impl<'a> Default for Context<'a> {
     #[allow(unreachable_code)]
     fn default() -> Self {
         #[cfg(target_os = "linux")]
-        return Context::V4l2(v4l2::context::Context {});
+        return Context::V4l2(v4l2::context::Context::new());
         #[cfg(feature = "plat-uvc")]
         return Context::Uvc(uvc::context::Context {});
     }
@@ -66,6 +165,33 @@ impl<'a> ContextTrait for Context<'a> {
         }
     }
 
+    fn find_devices<F>(&self, mut pred: F) -> Result<Vec<device::Description>>
+    where
+        F: FnMut(&dyn device::Info) -> bool,
+    {
+        match self {
+            Self::Custom(ctx) => Ok(ctx
+                .devices()?
+                .into_iter()
+                .filter(|desc| pred(desc))
+                .collect()),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(ctx) => ctx.find_devices(pred),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(ctx) => ctx.find_devices(pred),
+        }
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        match self {
+            Self::Custom(ctx) => ctx.refresh(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(ctx) => ctx.refresh(),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(ctx) => ctx.refresh(),
+        }
+    }
+
     fn open_device<'b>(&self, uri: &str) -> Result<Device<'b>> {
         match self {
             Self::Custom(ctx) => ctx.open_device(uri),
@@ -75,6 +201,19 @@ impl<'a> ContextTrait for Context<'a> {
             Self::Uvc(ctx) => ctx.open_device(uri),
         }
     }
+
+    fn estimate_bandwidth(
+        &self,
+        streams: &[(&str, &stream::Descriptor)],
+    ) -> device::BandwidthReport {
+        match self {
+            Self::Custom(ctx) => ctx.estimate_bandwidth(streams),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(ctx) => ctx.estimate_bandwidth(streams),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(ctx) => ctx.estimate_bandwidth(streams),
+        }
+    }
 }
 
 /// Platform device
@@ -94,6 +233,21 @@ pub enum Device<'a> {
     Uvc(uvc::device::Handle<'a>),
 }
 
+impl<'a> Device<'a> {
+    /// Returns a second handle to the same device, e.g. to adjust controls from another thread
+    ///
+    /// Only the V4L2 backend supports this, see
+    /// [`v4l2::device::Handle::try_clone`] for which operations are safe while the original
+    /// handle is streaming. All other devices return [`ErrorKind::NotSupported`].
+    pub fn try_clone(&self) -> Result<Device<'a>> {
+        match self {
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => Ok(Device::V4l2(dev.try_clone()?)),
+            _ => Err(Error::from(ErrorKind::NotSupported)),
+        }
+    }
+}
+
 impl<'a> DeviceTrait<'a> for Device<'a> {
     fn streams(&self) -> Result<Vec<stream::Descriptor>> {
         match self {
@@ -105,6 +259,100 @@ impl<'a> DeviceTrait<'a> for Device<'a> {
         }
     }
 
+    fn probe_streams(&self, timeout: Duration) -> Result<Vec<stream::Descriptor>> {
+        match self {
+            Self::Custom(dev) => dev.probe_streams(timeout),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.probe_streams(timeout),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.probe_streams(timeout),
+        }
+    }
+
+    fn still_streams(&self) -> Result<Vec<stream::Descriptor>> {
+        match self {
+            Self::Custom(dev) => dev.still_streams(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.still_streams(),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.still_streams(),
+        }
+    }
+
+    fn supports(&self, desc: &stream::Descriptor) -> bool {
+        match self {
+            Self::Custom(dev) => dev.supports(desc),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.supports(desc),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.supports(desc),
+        }
+    }
+
+    fn formats_at(&self, width: u32, height: u32) -> Result<Vec<PixelFormat>> {
+        match self {
+            Self::Custom(dev) => dev.formats_at(width, height),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.formats_at(width, height),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.formats_at(width, height),
+        }
+    }
+
+    fn supports_concurrent(&self, descs: &[stream::Descriptor]) -> bool {
+        match self {
+            Self::Custom(dev) => dev.supports_concurrent(descs),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.supports_concurrent(descs),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.supports_concurrent(descs),
+        }
+    }
+
+    fn capture_still(
+        &self,
+        pixfmt: PixelFormat,
+        warmup: usize,
+    ) -> Result<(Buffer<'static>, stream::Descriptor)> {
+        match self {
+            Self::Custom(dev) => dev.capture_still(pixfmt, warmup),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.capture_still(pixfmt, warmup),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.capture_still(pixfmt, warmup),
+        }
+    }
+
+    fn current_format(&self) -> Result<ImageFormat> {
+        match self {
+            Self::Custom(dev) => dev.current_format(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.current_format(),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.current_format(),
+        }
+    }
+
+    fn crop(&self) -> Result<Rect> {
+        match self {
+            Self::Custom(dev) => dev.crop(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.crop(),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.crop(),
+        }
+    }
+
+    fn set_crop(&mut self, rect: Rect) -> Result<Rect> {
+        match self {
+            Self::Custom(dev) => dev.set_crop(rect),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.set_crop(rect),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.set_crop(rect),
+        }
+    }
+
     fn controls(&self) -> Result<Vec<control::Descriptor>> {
         match self {
             Self::Custom(dev) => dev.controls(),
@@ -125,6 +373,26 @@ impl<'a> DeviceTrait<'a> for Device<'a> {
         }
     }
 
+    fn control_which(&self, id: u32, which: control::Which) -> Result<control::State> {
+        match self {
+            Self::Custom(dev) => dev.control_which(id, which),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.control_which(id, which),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.control_which(id, which),
+        }
+    }
+
+    fn control_id(&self, id: control::ControlId) -> Result<u32> {
+        match self {
+            Self::Custom(dev) => dev.control_id(id),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.control_id(id),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.control_id(id),
+        }
+    }
+
     fn set_control(&mut self, id: u32, val: &control::State) -> Result<()> {
         match self {
             Self::Custom(dev) => dev.set_control(id, val),
@@ -135,6 +403,36 @@ impl<'a> DeviceTrait<'a> for Device<'a> {
         }
     }
 
+    fn telemetry(&self) -> Result<Vec<(String, control::State)>> {
+        match self {
+            Self::Custom(dev) => dev.telemetry(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.telemetry(),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.telemetry(),
+        }
+    }
+
+    fn power_line_frequency(&self) -> Result<control::PowerLineFrequency> {
+        match self {
+            Self::Custom(dev) => dev.power_line_frequency(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.power_line_frequency(),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.power_line_frequency(),
+        }
+    }
+
+    fn set_power_line_frequency(&mut self, freq: control::PowerLineFrequency) -> Result<()> {
+        match self {
+            Self::Custom(dev) => dev.set_power_line_frequency(freq),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.set_power_line_frequency(freq),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.set_power_line_frequency(freq),
+        }
+    }
+
     fn start_stream(&self, settings: stream::DeviceStreamSettings) -> Result<Stream<'a>> {
         match self {
             Self::Custom(dev) => dev.start_stream(settings),
@@ -144,6 +442,180 @@ impl<'a> DeviceTrait<'a> for Device<'a> {
             Self::Uvc(dev) => dev.start_stream(settings),
         }
     }
+
+    fn start_dual_stream(
+        &self,
+        main: stream::DeviceStreamSettings,
+        secondary: stream::DeviceStreamSettings,
+    ) -> Result<(Stream<'a>, Stream<'a>)> {
+        match self {
+            Self::Custom(dev) => dev.start_dual_stream(main, secondary),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.start_dual_stream(main, secondary),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.start_dual_stream(main, secondary),
+        }
+    }
+
+    fn capture_burst(
+        &self,
+        desc: &stream::Descriptor,
+        count: usize,
+    ) -> Result<Vec<Buffer<'static>>> {
+        match self {
+            Self::Custom(dev) => dev.capture_burst(desc, count),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.capture_burst(desc, count),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.capture_burst(desc, count),
+        }
+    }
+
+    fn crop_caps(&self) -> Result<device::CropCaps> {
+        match self {
+            Self::Custom(dev) => dev.crop_caps(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.crop_caps(),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.crop_caps(),
+        }
+    }
+
+    fn set_focus_mode(&mut self, mode: control::FocusMode) -> Result<()> {
+        match self {
+            Self::Custom(dev) => dev.set_focus_mode(mode),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.set_focus_mode(mode),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.set_focus_mode(mode),
+        }
+    }
+
+    fn set_focus_absolute(&mut self, value: i64) -> Result<()> {
+        match self {
+            Self::Custom(dev) => dev.set_focus_absolute(value),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.set_focus_absolute(value),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.set_focus_absolute(value),
+        }
+    }
+
+    fn focus_distance(&self) -> Result<i64> {
+        match self {
+            Self::Custom(dev) => dev.focus_distance(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.focus_distance(),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.focus_distance(),
+        }
+    }
+
+    fn set_focus_range(&mut self, range: control::FocusRange) -> Result<()> {
+        match self {
+            Self::Custom(dev) => dev.set_focus_range(range),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.set_focus_range(range),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.set_focus_range(range),
+        }
+    }
+
+    fn gain_split_supported(&self) -> bool {
+        match self {
+            Self::Custom(dev) => dev.gain_split_supported(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.gain_split_supported(),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.gain_split_supported(),
+        }
+    }
+
+    fn set_analog_gain(&mut self, value: f64) -> Result<()> {
+        match self {
+            Self::Custom(dev) => dev.set_analog_gain(value),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.set_analog_gain(value),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.set_analog_gain(value),
+        }
+    }
+
+    fn set_digital_gain(&mut self, value: f64) -> Result<()> {
+        match self {
+            Self::Custom(dev) => dev.set_digital_gain(value),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.set_digital_gain(value),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.set_digital_gain(value),
+        }
+    }
+
+    fn set_metering_roi(&mut self, rect: Rect, mode: control::MeteringMode) -> Result<()> {
+        match self {
+            Self::Custom(dev) => dev.set_metering_roi(rect, mode),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.set_metering_roi(rect, mode),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.set_metering_roi(rect, mode),
+        }
+    }
+
+    fn lock_auto_values(&mut self) -> Result<()> {
+        match self {
+            Self::Custom(dev) => dev.lock_auto_values(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.lock_auto_values(),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.lock_auto_values(),
+        }
+    }
+
+    fn uvc_xu_query(
+        &mut self,
+        unit: u8,
+        selector: u8,
+        query: control::XuQuery,
+        data: &mut [u8],
+    ) -> Result<()> {
+        match self {
+            Self::Custom(dev) => dev.uvc_xu_query(unit, selector, query, data),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.uvc_xu_query(unit, selector, query, data),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.uvc_xu_query(unit, selector, query, data),
+        }
+    }
+
+    fn capabilities(&self) -> Result<device::Capabilities> {
+        match self {
+            Self::Custom(dev) => dev.capabilities(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.capabilities(),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.capabilities(),
+        }
+    }
+
+    fn audio_inputs(&self) -> Result<Vec<device::AudioInput>> {
+        match self {
+            Self::Custom(dev) => dev.audio_inputs(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.audio_inputs(),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.audio_inputs(),
+        }
+    }
+
+    fn log_status(&self) -> Result<()> {
+        match self {
+            Self::Custom(dev) => dev.log_status(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(dev) => dev.log_status(),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(dev) => dev.log_status(),
+        }
+    }
 }
 
 /// Platform stream
@@ -154,6 +626,9 @@ impl<'a> DeviceTrait<'a> for Device<'a> {
 /// A stream is used read frames from a camera device. Many HAL implementations feature advanced
 /// I/O method such as memory mapped streaming, DMA and more. We attempt to automatically select
 /// the best method available.
+// Streams are created once and rarely moved, so the handles are not boxed to keep them easy to
+// match on.
+#[allow(clippy::large_enum_variant)]
 pub enum Stream<'a> {
     /// Can be used to wrap your own struct
     Custom(Box<dyn 'a + for<'b> StreamTrait<'b, Item = Result<Buffer<'b>>> + Send>),
@@ -177,4 +652,124 @@ impl<'a, 'b> StreamTrait<'b> for Stream<'a> {
             Self::Uvc(stream) => stream.next(),
         }
     }
+
+    fn buffer_count(&self) -> Option<usize> {
+        match self {
+            Self::Custom(stream) => stream.buffer_count(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(stream) => StreamTrait::buffer_count(stream),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(stream) => StreamTrait::buffer_count(stream),
+        }
+    }
+
+    fn last_buffer_index(&self) -> Option<u32> {
+        match self {
+            Self::Custom(stream) => stream.last_buffer_index(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(stream) => StreamTrait::last_buffer_index(stream),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(stream) => StreamTrait::last_buffer_index(stream),
+        }
+    }
+
+    fn queue_state(&self) -> Option<stream::QueueState> {
+        match self {
+            Self::Custom(stream) => stream.queue_state(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(stream) => StreamTrait::queue_state(stream),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(stream) => StreamTrait::queue_state(stream),
+        }
+    }
+
+    fn start_instant(&self) -> Option<Instant> {
+        match self {
+            Self::Custom(stream) => stream.start_instant(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(stream) => StreamTrait::start_instant(stream),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(stream) => StreamTrait::start_instant(stream),
+        }
+    }
+
+    fn first_frame_latency(&self) -> Option<Duration> {
+        match self {
+            Self::Custom(stream) => stream.first_frame_latency(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(stream) => StreamTrait::first_frame_latency(stream),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(stream) => StreamTrait::first_frame_latency(stream),
+        }
+    }
+
+    fn descriptor(&self) -> Option<stream::Descriptor> {
+        match self {
+            Self::Custom(stream) => stream.descriptor(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(stream) => StreamTrait::descriptor(stream),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(stream) => StreamTrait::descriptor(stream),
+        }
+    }
+
+    fn set_interval(&mut self, interval: Duration) -> Result<()> {
+        match self {
+            Self::Custom(stream) => stream.set_interval(interval),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(stream) => StreamTrait::set_interval(stream, interval),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(stream) => StreamTrait::set_interval(stream, interval),
+        }
+    }
+
+    fn set_buffer_count(&mut self, count: usize) -> Result<()> {
+        match self {
+            Self::Custom(stream) => stream.set_buffer_count(count),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(stream) => StreamTrait::set_buffer_count(stream, count),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(stream) => StreamTrait::set_buffer_count(stream, count),
+        }
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        match self {
+            Self::Custom(stream) => stream.pause(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(stream) => StreamTrait::pause(stream),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(stream) => StreamTrait::pause(stream),
+        }
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        match self {
+            Self::Custom(stream) => stream.resume(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(stream) => StreamTrait::resume(stream),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(stream) => StreamTrait::resume(stream),
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        match self {
+            Self::Custom(stream) => stream.is_paused(),
+            #[cfg(target_os = "linux")]
+            Self::V4l2(stream) => StreamTrait::is_paused(stream),
+            #[cfg(feature = "plat-uvc")]
+            Self::Uvc(stream) => StreamTrait::is_paused(stream),
+        }
+    }
+}
+
+/// Converts a name which a driver copied into a fixed size C string buffer
+///
+/// The name ends at the first NUL byte, since some drivers leave garbage after the terminator.
+/// Invalid UTF-8 sequences are replaced instead of rejecting the whole name.
+#[cfg(any(target_os = "linux", feature = "plat-uvc"))]
+pub(crate) fn clean_name(name: &[u8]) -> String {
+    let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    String::from_utf8_lossy(&name[..len]).trim_end().to_string()
 }
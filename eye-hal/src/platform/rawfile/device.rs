@@ -0,0 +1,114 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::control;
+use crate::error::{Error, ErrorKind, Result};
+use crate::platform::rawfile::stream::{Handle as StreamHandle, Playback};
+use crate::platform::Stream as PlatformStream;
+use crate::stream::{Descriptor, DeviceStreamSettings};
+use crate::traits::Device;
+
+pub struct Handle {
+    path: PathBuf,
+    desc: Descriptor,
+    frame_size: usize,
+    playback: Playback,
+}
+
+impl Handle {
+    /// Returns a device for a file of raw frames as described by `desc`
+    ///
+    /// Only uncompressed formats are supported, since compressed frames vary in size.
+    pub fn new<P: AsRef<Path>>(path: P, desc: Descriptor) -> Result<Self> {
+        let frame_size = match desc.pixfmt.planes(desc.width, desc.height) {
+            Some(planes) => planes.len,
+            None => match desc.pixfmt.bits() {
+                Some(bits) => (desc.width as usize * desc.height as usize * bits as usize) / 8,
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::NotSupported,
+                        format!("cannot determine the frame size of {}", desc.pixfmt),
+                    ))
+                }
+            },
+        };
+
+        let path = path.as_ref().to_path_buf();
+        let len = fs::metadata(&path)?.len();
+        if frame_size == 0 || len < frame_size as u64 {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "file does not contain a single frame",
+            ));
+        }
+
+        Ok(Handle {
+            path,
+            desc,
+            frame_size,
+            playback: Playback::Loop,
+        })
+    }
+
+    /// Sets what happens at the end of the file, playback loops by default
+    pub fn with_playback(mut self, playback: Playback) -> Self {
+        self.playback = playback;
+        self
+    }
+
+    /// Returns a stream of the frames in the file, paced at the given interval
+    ///
+    /// Unlike [`Device::start_stream`], this returns the concrete stream type, which supports
+    /// seeking to a frame.
+    pub fn open_stream(&self, interval: Duration) -> Result<StreamHandle> {
+        let desc = Descriptor {
+            interval,
+            ..self.desc.clone()
+        };
+        let file = File::open(&self.path)?;
+        Ok(StreamHandle::new(file, desc, self.frame_size).with_playback(self.playback))
+    }
+}
+
+impl<'a> Device<'a> for Handle {
+    fn streams(&self) -> Result<Vec<Descriptor>> {
+        Ok(vec![self.desc.clone()])
+    }
+
+    fn start_stream(&self, settings: DeviceStreamSettings) -> Result<PlatformStream<'a>> {
+        let desc = settings.desc;
+        if desc.width != self.desc.width
+            || desc.height != self.desc.height
+            || desc.pixfmt != self.desc.pixfmt
+        {
+            return Err(Error::new(
+                ErrorKind::NotSupported,
+                "the file only contains frames of a single format",
+            ));
+        }
+
+        // Frames are paced at the requested interval, so it may differ from the one the file
+        // was captured at (e.g. to replay it as fast as possible).
+        let stream = self.open_stream(desc.interval)?;
+        Ok(PlatformStream::Custom(Box::new(stream)))
+    }
+
+    fn controls(&self) -> Result<Vec<control::Descriptor>> {
+        Ok(Vec::new())
+    }
+
+    fn control(&self, _id: u32) -> Result<control::State> {
+        Err(Error::new(
+            ErrorKind::NotSupported,
+            "device has no controls",
+        ))
+    }
+
+    fn set_control(&mut self, _id: u32, _val: &control::State) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::NotSupported,
+            "device has no controls",
+        ))
+    }
+}
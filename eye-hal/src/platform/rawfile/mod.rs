@@ -0,0 +1,30 @@
+//! Raw video file backend
+//!
+//! Treats a file of concatenated raw frames (e.g. a `.yuv` dump) as a capture device. Since such
+//! files carry no header, the stream configuration must be supplied by the caller. Frames are
+//! paced at the frame interval and playback wraps around at the end of the file (or ends the
+//! stream, see [`stream::Playback`]), which makes this useful to reproduce issues with captured
+//! data and to benchmark processing pipelines deterministically. Streams opened with
+//! [`device::Handle::open_stream`] can seek to a specific frame.
+//!
+//! There is no context for this backend, devices are created directly:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use eye_hal::format::PixelFormat;
+//! use eye_hal::platform::{rawfile, Device};
+//! use eye_hal::stream::Descriptor;
+//!
+//! let desc = Descriptor {
+//!     width: 1280,
+//!     height: 720,
+//!     pixfmt: PixelFormat::Nv12,
+//!     interval: Duration::from_secs_f64(1.0 / 30.0),
+//!     emulated: false,
+//! };
+//! let dev = rawfile::device::Handle::new("capture.nv12", desc).unwrap();
+//! let dev = Device::Custom(Box::new(dev));
+//! ```
+
+pub mod device;
+pub mod stream;
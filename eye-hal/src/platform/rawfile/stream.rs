@@ -0,0 +1,135 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::buffer::{Buffer, Metadata};
+use crate::error::{Error, ErrorKind, Result};
+use crate::stream::Descriptor;
+use crate::traits::Stream;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// What happens at the end of the file
+pub enum Playback {
+    /// Start over with the first frame
+    Loop,
+    /// End the stream
+    Once,
+}
+
+pub struct Handle {
+    file: File,
+    desc: Descriptor,
+    buf: Vec<u8>,
+    playback: Playback,
+    sequence: u32,
+    /// Time at which the first frame was returned
+    start: Option<Instant>,
+    /// Time and sequence number which the pacing is based on
+    base: Option<(Instant, u32)>,
+}
+
+impl Handle {
+    pub fn new(file: File, desc: Descriptor, frame_size: usize) -> Self {
+        Handle {
+            file,
+            desc,
+            buf: vec![0; frame_size],
+            playback: Playback::Loop,
+            sequence: 0,
+            start: None,
+            base: None,
+        }
+    }
+
+    /// Sets what happens at the end of the file, playback loops by default
+    pub fn with_playback(mut self, playback: Playback) -> Self {
+        self.playback = playback;
+        self
+    }
+
+    /// Continues playback at the frame with the given index
+    ///
+    /// The next call to [`Stream::next`] returns that frame right away, later ones are paced
+    /// from there. Sequence numbers keep counting the returned frames, so they do not jump. This
+    /// also restarts a stream which ended in [`Playback::Once`] mode.
+    pub fn seek(&mut self, index: u64) -> Result<()> {
+        let frame_size = self.buf.len() as u64;
+        let frames = self.file.metadata()?.len() / frame_size;
+        if index >= frames {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!(
+                    "frame {} is out of range, the file has {} frames",
+                    index, frames
+                ),
+            ));
+        }
+
+        self.file.seek(SeekFrom::Start(index * frame_size))?;
+        self.base = None;
+        Ok(())
+    }
+
+    /// Reads the next frame, returns `false` once playback ended
+    ///
+    /// A trailing partial frame is skipped.
+    fn read_frame(&mut self) -> io::Result<bool> {
+        match self.file.read_exact(&mut self.buf) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => match self.playback {
+                Playback::Loop => {
+                    self.file.seek(SeekFrom::Start(0))?;
+                    self.file.read_exact(&mut self.buf)?;
+                    Ok(true)
+                }
+                Playback::Once => Ok(false),
+            },
+            res => res.map(|_| true),
+        }
+    }
+
+    /// Waits until the current frame is due
+    fn pace(&mut self) {
+        let (time, sequence) = *self.base.get_or_insert((Instant::now(), self.sequence));
+        // Computing the deadline from a fixed base avoids accumulating drift.
+        let deadline = time + self.desc.interval * self.sequence.wrapping_sub(sequence);
+        let now = Instant::now();
+        if deadline > now {
+            thread::sleep(deadline - now);
+        }
+    }
+}
+
+impl<'a> Stream<'a> for Handle {
+    type Item = Result<Buffer<'a>>;
+
+    fn next(&'a mut self) -> Option<Self::Item> {
+        match self.read_frame() {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(e) => return Some(Err(e.into())),
+        }
+        self.pace();
+
+        let now = Instant::now();
+        let start = *self.start.get_or_insert(now);
+        let meta = Metadata {
+            sequence: Some(self.sequence),
+            timestamp: Some(now - start),
+            ..Metadata::default()
+        };
+        self.sequence = self.sequence.wrapping_add(1);
+
+        Some(Ok(Buffer::from(&self.buf[..]).with_meta(meta)))
+    }
+
+    fn descriptor(&self) -> Option<Descriptor> {
+        Some(self.desc.clone())
+    }
+
+    fn set_interval(&mut self, interval: Duration) -> Result<()> {
+        self.desc.interval = interval;
+        self.base = None;
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use crate::device;
+use crate::error::{Error, ErrorKind, Result};
+use crate::platform::scripted::device::Handle as DeviceHandle;
+use crate::platform::Device as PlatformDevice;
+use crate::traits::Context as ContextTrait;
+
+#[derive(Clone, Default)]
+pub struct Context {
+    devices: Vec<(String, DeviceHandle)>,
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a device, each call to [`ContextTrait::open_device`] returns a copy of it
+    pub fn with_device<S: Into<String>>(mut self, product: S, dev: DeviceHandle) -> Self {
+        self.devices.push((product.into(), dev));
+        self
+    }
+}
+
+impl ContextTrait for Context {
+    fn devices(&self) -> Result<Vec<device::Description>> {
+        Ok(self
+            .devices
+            .iter()
+            .enumerate()
+            .map(|(index, (product, _))| device::Description {
+                uri: format!("scripted://{}", index),
+                product: product.clone(),
+                exclusive: false,
+                bus_path: None,
+            })
+            .collect())
+    }
+
+    fn open_device<'a>(&self, uri: &str) -> Result<PlatformDevice<'a>> {
+        let dev = uri
+            .strip_prefix("scripted://")
+            .and_then(|index| index.parse::<usize>().ok())
+            .and_then(|index| self.devices.get(index));
+
+        match dev {
+            Some((_, dev)) => Ok(PlatformDevice::Custom(Box::new(dev.clone()))),
+            None => Err(Error::new(ErrorKind::Other, "no such device")),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use crate::control;
+use crate::error::{Error, ErrorKind, Result};
+use crate::platform::scripted::stream::Handle as StreamHandle;
+use crate::platform::scripted::Event;
+use crate::platform::Stream as PlatformStream;
+use crate::stream::{Descriptor, DeviceStreamSettings};
+use crate::traits::Device;
+
+#[derive(Clone)]
+pub struct Handle {
+    desc: Descriptor,
+    events: Vec<Event>,
+    controls: Vec<(control::Descriptor, control::State)>,
+}
+
+impl Handle {
+    /// Returns a device with a single stream which replays `events`
+    ///
+    /// Every stream started on the device replays the events from the beginning.
+    pub fn new(desc: Descriptor, events: Vec<Event>) -> Self {
+        Handle {
+            desc,
+            events,
+            controls: Vec::new(),
+        }
+    }
+
+    /// Adds a control with an initial value
+    pub fn with_control(mut self, desc: control::Descriptor, value: control::State) -> Self {
+        self.controls.push((desc, value));
+        self
+    }
+
+    fn find(&self, id: u32) -> Result<usize> {
+        self.controls
+            .iter()
+            .position(|(desc, _)| desc.id == id)
+            .ok_or_else(|| Error::new(ErrorKind::NotSupported, "no such control"))
+    }
+}
+
+impl<'a> Device<'a> for Handle {
+    fn streams(&self) -> Result<Vec<Descriptor>> {
+        Ok(vec![self.desc.clone()])
+    }
+
+    fn start_stream(&self, settings: DeviceStreamSettings) -> Result<PlatformStream<'a>> {
+        let desc = settings.desc;
+        if desc.width != self.desc.width
+            || desc.height != self.desc.height
+            || desc.pixfmt != self.desc.pixfmt
+        {
+            return Err(Error::new(
+                ErrorKind::NotSupported,
+                "the device only has a single stream",
+            ));
+        }
+
+        let stream = StreamHandle::new(self.desc.clone(), self.events.clone());
+        Ok(PlatformStream::Custom(Box::new(stream)))
+    }
+
+    fn controls(&self) -> Result<Vec<control::Descriptor>> {
+        Ok(self.controls.iter().map(|(desc, _)| desc.clone()).collect())
+    }
+
+    fn control(&self, id: u32) -> Result<control::State> {
+        let (desc, value) = &self.controls[self.find(id)?];
+        if !desc.readable() {
+            return Err(Error::new(ErrorKind::Other, "control is not readable"));
+        }
+
+        Ok(value.clone())
+    }
+
+    fn set_control(&mut self, id: u32, val: &control::State) -> Result<()> {
+        let index = self.find(id)?;
+        let (desc, value) = &mut self.controls[index];
+        if !desc.writable() {
+            return Err(Error::new(ErrorKind::Other, "control is not writable"));
+        }
+
+        *value = val.clone();
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+//! Scripted test backend
+//!
+//! Devices of this backend replay a fixed list of events instead of capturing frames, which makes
+//! it possible to test stream consumers (e.g. stream adapters) without hardware. Scripts can
+//! simulate the conditions real devices run into, such as truncated frames, dropped frames or
+//! errors.
+//!
+//! This backend is only available with the `testutil` feature.
+//!
+//! ```
+//! use std::time::Duration;
+//! use eye_hal::format::PixelFormat;
+//! use eye_hal::platform::scripted::{self, Event};
+//! use eye_hal::stream::{Descriptor, DeviceStreamSettings};
+//! use eye_hal::traits::{Device, Stream};
+//! use eye_hal::ErrorKind;
+//!
+//! let desc = Descriptor {
+//!     width: 2,
+//!     height: 2,
+//!     pixfmt: PixelFormat::Gray(8),
+//!     interval: Duration::from_millis(33),
+//!     emulated: false,
+//! };
+//! let dev = scripted::device::Handle::new(
+//!     desc.clone(),
+//!     vec![
+//!         Event::Frame(vec![0; 4]),
+//!         Event::Gap(2),
+//!         Event::Frame(vec![0; 3]),
+//!         Event::Error(ErrorKind::Other),
+//!     ],
+//! );
+//!
+//! let mut stream = dev.start_stream(DeviceStreamSettings::new(&desc)).unwrap();
+//! assert_eq!(stream.next().unwrap().unwrap().meta().sequence, Some(0));
+//! assert_eq!(stream.next().unwrap().unwrap().meta().sequence, Some(3));
+//! assert!(stream.next().unwrap().is_err());
+//! assert!(stream.next().is_none());
+//! ```
+
+pub mod context;
+pub mod device;
+pub mod stream;
+
+use crate::error::ErrorKind;
+
+#[derive(Clone, Debug)]
+/// Scripted stream event
+pub enum Event {
+    /// Emits a frame with the given data
+    ///
+    /// The data does not have to match the stream format, e.g. to simulate truncated frames.
+    Frame(Vec<u8>),
+    /// Skips sequence numbers, as if the driver dropped the given number of frames
+    Gap(u32),
+    /// Emits an error of the given kind
+    ///
+    /// Use [`ErrorKind::Other`] to simulate transient errors such as `EAGAIN`.
+    Error(ErrorKind),
+    /// Ends the stream, regardless of the remaining events
+    End,
+}
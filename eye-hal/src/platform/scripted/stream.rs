@@ -0,0 +1,78 @@
+use std::vec;
+
+use crate::buffer::{Buffer, Metadata};
+use crate::error::{Error, Result};
+use crate::platform::scripted::Event;
+use crate::stream::Descriptor;
+use crate::traits::Stream;
+
+pub struct Handle {
+    desc: Descriptor,
+    events: vec::IntoIter<Event>,
+    buf: Vec<u8>,
+    sequence: u32,
+    paused: bool,
+}
+
+impl Handle {
+    pub fn new(desc: Descriptor, events: Vec<Event>) -> Self {
+        Handle {
+            desc,
+            events: events.into_iter(),
+            buf: Vec::new(),
+            sequence: 0,
+            paused: false,
+        }
+    }
+}
+
+impl<'a> Stream<'a> for Handle {
+    type Item = Result<Buffer<'a>>;
+
+    fn next(&'a mut self) -> Option<Self::Item> {
+        if self.paused {
+            return None;
+        }
+
+        loop {
+            match self.events.next()? {
+                Event::Frame(data) => {
+                    // Hand out a borrowed buffer like real backends do.
+                    self.buf = data;
+                    let meta = Metadata {
+                        sequence: Some(self.sequence),
+                        timestamp: Some(self.desc.interval * self.sequence),
+                        ..Metadata::default()
+                    };
+                    self.sequence = self.sequence.wrapping_add(1);
+                    return Some(Ok(Buffer::from(&self.buf[..]).with_meta(meta)));
+                }
+                Event::Gap(frames) => self.sequence = self.sequence.wrapping_add(frames),
+                Event::Error(kind) => return Some(Err(Error::new(kind, "scripted error"))),
+                Event::End => {
+                    self.events = Vec::new().into_iter();
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn descriptor(&self) -> Option<Descriptor> {
+        Some(self.desc.clone())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        // The remaining events are replayed once the stream is resumed.
+        self.paused = true;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.paused = false;
+        Ok(())
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
@@ -1,6 +1,6 @@
 use crate::device;
 use crate::error::{Error, ErrorKind, Result};
-use crate::platform::Device as PlatformDevice;
+use crate::platform::{clean_name, Device as PlatformDevice};
 use crate::traits::Context as ContextTrait;
 
 /// Runtime context
@@ -20,11 +20,14 @@ impl ContextTrait for Context {
                     let mut description = device::Description {
                         uri: format!("uvc://{}:{}", dev.bus_number(), dev.device_address()),
                         product: "Unknown UVC device".to_string(),
+                        // libusb claims the interfaces of the device
+                        exclusive: true,
+                        bus_path: None,
                     };
 
                     if let Ok(desc) = dev.description() {
                         if let Some(product) = desc.product {
-                            description.product = product;
+                            description.product = clean_name(product.as_bytes());
                         }
                     }
 
@@ -47,8 +50,17 @@ impl ContextTrait for Context {
         if uri.starts_with("uvc://") {
             let handle = match crate::platform::uvc::device::Handle::with_uri(uri) {
                 Ok(handle) => handle,
-                Err(e) => return Err(Error::new(ErrorKind::Other, format!("UVC: {}", e))),
+                Err(e) => {
+                    error!(uri, error = %e, "failed to open UVC device");
+                    let kind = match e {
+                        uvc::Error::Busy => ErrorKind::DeviceBusy,
+                        uvc::Error::Access => ErrorKind::PermissionDenied,
+                        _ => ErrorKind::Other,
+                    };
+                    return Err(Error::new(kind, format!("UVC: {}", e)));
+                }
             };
+            debug!(uri, "opened UVC device");
             Ok(PlatformDevice::Uvc(handle))
         } else {
             Err(Error::new(ErrorKind::Other, "invalid URI"))
@@ -99,30 +99,39 @@ impl From<&Control> for control::Descriptor {
                 id: ctrl.id(),
                 name: String::from(ctrl.name()),
                 flags: control::Flags::READ,
-                typ: control::Type::Menu(vec![
-                    control::MenuItem::String(String::from("Interlaced")),
-                    control::MenuItem::String(String::from("Progressive")),
-                ]),
+                typ: control::Type::Menu {
+                    items: vec![
+                        control::MenuItem::String(String::from("Interlaced")),
+                        control::MenuItem::String(String::from("Progressive")),
+                    ],
+                    default: None,
+                },
             },
             Control::AutoExposureMode => control::Descriptor {
                 id: ctrl.id(),
                 name: String::from(ctrl.name()),
                 flags: control::Flags::READ,
-                typ: control::Type::Menu(vec![
-                    control::MenuItem::String(String::from("Manual")),
-                    control::MenuItem::String(String::from("Auto")),
-                    control::MenuItem::String(String::from("ShutterPriority")),
-                    control::MenuItem::String(String::from("AperturePriority")),
-                ]),
+                typ: control::Type::Menu {
+                    items: vec![
+                        control::MenuItem::String(String::from("Manual")),
+                        control::MenuItem::String(String::from("Auto")),
+                        control::MenuItem::String(String::from("ShutterPriority")),
+                        control::MenuItem::String(String::from("AperturePriority")),
+                    ],
+                    default: None,
+                },
             },
             Control::AutoExposurePriority => control::Descriptor {
                 id: ctrl.id(),
                 name: String::from(ctrl.name()),
                 flags: control::Flags::READ,
-                typ: control::Type::Menu(vec![
-                    control::MenuItem::String(String::from("Constant")),
-                    control::MenuItem::String(String::from("Variable")),
-                ]),
+                typ: control::Type::Menu {
+                    items: vec![
+                        control::MenuItem::String(String::from("Constant")),
+                        control::MenuItem::String(String::from("Variable")),
+                    ],
+                    default: None,
+                },
             },
             Control::ExposureAbsolute => control::Descriptor {
                 id: ctrl.id(),
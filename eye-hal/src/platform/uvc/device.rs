@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use crate::control;
+use crate::device;
 use crate::error::{Error, ErrorKind, Result};
 use crate::format::PixelFormat;
 use crate::platform::uvc::control::Control;
@@ -73,6 +74,7 @@ impl<'a> Device<'a> for Handle<'a> {
                                 height: frame_desc.height() as u32,
                                 pixfmt: pixfmt.clone(),
                                 interval,
+                                emulated: false,
                             });
                         }
                     });
@@ -96,17 +98,33 @@ impl<'a> Device<'a> for Handle<'a> {
         }
     }
 
+    fn control_id(&self, id: control::ControlId) -> Result<u32> {
+        let ctrl = match id {
+            control::ControlId::Exposure => Control::ExposureAbsolute,
+            control::ControlId::AutoExposure => Control::AutoExposureMode,
+            control::ControlId::Focus => Control::FocusAbsolute,
+            control::ControlId::Raw(id) => return Ok(id),
+            _ => return Err(Error::from(ErrorKind::NotSupported)),
+        };
+        Ok(ctrl.id())
+    }
+
     fn set_control(&mut self, _id: u32, _val: &control::State) -> Result<()> {
         Err(Error::from(ErrorKind::NotSupported))
     }
 
+    fn capabilities(&self) -> Result<device::Capabilities> {
+        // libuvc always streams through isochronous or bulk transfers
+        Ok(device::Capabilities::VIDEO_CAPTURE | device::Capabilities::STREAMING)
+    }
+
     fn start_stream(&self, settings: stream::DeviceStreamSettings) -> Result<PlatformStream<'a>> {
         let desc = settings.desc;
         let dev_handle = self.inner.clone();
         let dev_handle_ptr = &*dev_handle.handle as *const uvc::DeviceHandle;
         let dev_handle_ref = unsafe { &*dev_handle_ptr as &uvc::DeviceHandle };
 
-        let desc_fps = (1.0 / desc.interval.as_secs_f64()) as u64;
+        let desc_fps = desc.fps().round() as u64;
         let stream_format = self.inner.handle.get_preferred_format(|x, y| {
             if x.width == desc.width && x.height == desc.height && x.fps as u64 >= desc_fps {
                 x
@@ -127,6 +145,12 @@ impl<'a> Device<'a> for Handle<'a> {
             }
             None => return Err(Error::new(ErrorKind::Other, "failed to query formats")),
         };
+        debug!(
+            width = stream_format.width,
+            height = stream_format.height,
+            fps = stream_format.fps,
+            "negotiated format"
+        );
 
         let stream_handle = match dev_handle_ref.get_stream_handle_with_format(stream_format) {
             Ok(handle) => handle,
@@ -138,7 +162,12 @@ impl<'a> Device<'a> for Handle<'a> {
             stream_handle,
             settings.buffers_count.unwrap_or(0),
         ) {
-            Ok(handle) => Ok(PlatformStream::Uvc(handle)),
+            Ok(handle) => Ok(PlatformStream::Uvc(
+                handle
+                    .with_consume_policy(settings.consume_policy)
+                    .with_timeout(settings.timeout)
+                    .with_warmup_frames(settings.warmup_frames),
+            )),
             Err(e) => Err(Error::new(ErrorKind::Other, e)),
         }
     }
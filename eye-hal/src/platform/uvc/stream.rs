@@ -1,12 +1,18 @@
 use std::sync::{mpsc, Arc};
+use std::time::Duration;
 
 use crate::buffer::Buffer;
-use crate::error::Result;
+use crate::error::{Error, ErrorKind, Result};
 use crate::platform::uvc::device::UvcHandle;
+use crate::stream::ConsumePolicy;
 use crate::traits::Stream;
 
 pub struct Handle<'a> {
     rx: mpsc::Receiver<uvc::Result<uvc::Frame>>,
+    consume_policy: ConsumePolicy,
+    timeout: Option<Duration>,
+    /// Number of warmup frames which are still to be discarded
+    skip: usize,
 
     // these are required to keep the frame callback alive
     _stream: uvc::ActiveStream<'a, mpsc::SyncSender<uvc::Result<uvc::Frame>>>,
@@ -40,18 +46,69 @@ impl<'a> Handle<'a> {
 
         Ok(Handle {
             rx,
+            consume_policy: ConsumePolicy::default(),
+            timeout: None,
+            skip: 0,
             _stream: stream,
             _stream_handle: stream_handle,
             _dev_handle: dev_handle,
         })
     }
+
+    /// Selects whether stale frames are dropped
+    pub fn with_consume_policy(mut self, policy: ConsumePolicy) -> Self {
+        self.consume_policy = policy;
+        self
+    }
+
+    /// Limits the time to wait for a frame
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Discards a number of frames once the stream started
+    pub fn with_warmup_frames(mut self, count: usize) -> Self {
+        self.skip = count;
+        self
+    }
+
+    fn recv(&self) -> Option<Result<uvc::Result<uvc::Frame>>> {
+        match self.timeout {
+            Some(timeout) => match self.rx.recv_timeout(timeout) {
+                Ok(frame) => Some(Ok(frame)),
+                Err(mpsc::RecvTimeoutError::Timeout) => Some(Err(Error::new(
+                    ErrorKind::Timeout,
+                    "no frame arrived in time",
+                ))),
+                Err(mpsc::RecvTimeoutError::Disconnected) => None,
+            },
+            None => self.rx.recv().ok().map(Ok),
+        }
+    }
 }
 
 impl<'a, 'b> Stream<'b> for Handle<'a> {
     type Item = Result<Buffer<'b>>;
 
     fn next(&'b mut self) -> Option<Self::Item> {
-        let frame = self.rx.recv().unwrap();
+        while self.skip > 0 {
+            if let Err(e) = self.recv()? {
+                return Some(Err(e));
+            }
+            self.skip -= 1;
+        }
+
+        let mut frame = match self.recv()? {
+            Ok(frame) => frame,
+            Err(e) => return Some(Err(e)),
+        };
+        if self.consume_policy == ConsumePolicy::KeepLatest {
+            // The frame callback blocks while the channel is full, draining it lets it catch up.
+            while let Ok(newer) = self.rx.try_recv() {
+                frame = newer;
+            }
+        }
         let pixels = match &frame {
             Ok(frame) => frame.to_bytes(),
             Err(_) => {
@@ -1,58 +1,329 @@
+use std::cell::RefCell;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::path::Path;
+
 use v4l::context;
 
 use crate::device;
 use crate::error::{Error, ErrorKind, Result};
+use crate::platform::v4l2::capabilities;
 use crate::platform::v4l2::device::Handle;
-use crate::platform::Device as PlatformDevice;
+use crate::platform::{clean_name, Device as PlatformDevice};
+use crate::stream;
 use crate::traits::Context as ContextTrait;
 
 /// Runtime context
-pub struct Context {}
+///
+/// Until [`refresh`](ContextTrait::refresh) is called, every call to
+/// [`devices`](ContextTrait::devices) opens all video nodes to query their capabilities. Once the
+/// device list is cached, refreshing it only queries nodes which were added since.
+#[derive(Default)]
+pub struct Context {
+    cache: Option<Vec<Node>>,
+    /// Whether devices are opened bypassing libv4l
+    native_only: bool,
+    /// Memory backing the stream buffers of the devices opened through the context
+    memory: stream::MemoryType,
+}
+
+/// Cached state of a video node
+struct Node {
+    index: usize,
+    /// Inode of the device file, which changes when a device is unplugged and plugged in again
+    ino: Option<u64>,
+    /// Device description, `None` if the node is not a capture device
+    desc: Option<device::Description>,
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Context {
+            cache: None,
+            native_only: false,
+            memory: stream::MemoryType::default(),
+        }
+    }
+
+    /// Returns a context whose devices bypass libv4l
+    ///
+    /// Every device returned by [`open_device`](ContextTrait::open_device) is opened like
+    /// [`Self::open_device_raw`] does. Their [`streams`](crate::traits::Device::streams) only
+    /// contain the formats natively produced by the hardware, formats emulated by libv4l (e.g.
+    /// RGB for MJPEG cameras) are not offered, and frames are never converted in software.
+    pub fn native_only() -> Self {
+        Context {
+            cache: None,
+            native_only: true,
+            memory: stream::MemoryType::default(),
+        }
+    }
+
+    /// Returns whether devices are opened bypassing libv4l
+    pub fn is_native_only(&self) -> bool {
+        self.native_only
+    }
+
+    /// Selects the memory backing the stream buffers of all devices opened through the context
+    ///
+    /// Streams may still select a different memory type through
+    /// [`DeviceStreamSettings::with_memory`](stream::DeviceStreamSettings::with_memory).
+    pub fn with_memory(mut self, memory: stream::MemoryType) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Returns the memory backing the stream buffers by default
+    pub fn memory(&self) -> stream::MemoryType {
+        self.memory
+    }
+
+    /// Returns a device for an already opened file descriptor
+    ///
+    /// See [`Handle::from_raw_fd`] for details.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `fd` is an open file descriptor referring to a V4L2 device.
+    /// Ownership of the descriptor is transferred to the returned device.
+    pub unsafe fn open_device_from_raw_fd<'a>(&self, fd: RawFd) -> Result<PlatformDevice<'a>> {
+        Ok(PlatformDevice::V4l2(
+            Handle::from_raw_fd(fd).with_memory(self.memory),
+        ))
+    }
+
+    /// Opens a device handle, bypassing libv4l
+    ///
+    /// See [`Handle::with_path_raw`] for details.
+    pub fn open_device_raw<'a>(&self, uri: &str) -> Result<PlatformDevice<'a>> {
+        if let Some(path) = uri.strip_prefix("v4l://") {
+            Ok(PlatformDevice::V4l2(
+                Handle::with_path_raw(path)?.with_memory(self.memory),
+            ))
+        } else {
+            Err(Error::new(ErrorKind::Other, "invalid URI"))
+        }
+    }
+
+    /// Opens a device handle for inspection only
+    ///
+    /// See [`Handle::with_path_read_only`] for details.
+    pub fn open_device_read_only<'a>(&self, uri: &str) -> Result<PlatformDevice<'a>> {
+        if let Some(path) = uri.strip_prefix("v4l://") {
+            Ok(PlatformDevice::V4l2(
+                Handle::with_path_read_only(path)?.with_memory(self.memory),
+            ))
+        } else {
+            Err(Error::new(ErrorKind::Other, "invalid URI"))
+        }
+    }
+}
 
 impl ContextTrait for Context {
     fn devices(&self) -> Result<Vec<device::Description>> {
+        let nodes = match &self.cache {
+            Some(cache) => cache.iter().filter_map(|node| node.desc.clone()).collect(),
+            None => context::enum_devices()
+                .into_iter()
+                .filter_map(|node| describe(node.index()))
+                .collect(),
+        };
+
+        Ok(nodes)
+    }
+
+    fn find_devices<F>(&self, mut pred: F) -> Result<Vec<device::Description>>
+    where
+        F: FnMut(&dyn device::Info) -> bool,
+    {
+        if self.cache.is_some() {
+            return Ok(self
+                .devices()?
+                .into_iter()
+                .filter(|desc| pred(desc))
+                .collect());
+        }
+
+        // Only open the nodes the predicate does not reject right away.
+        let devices = context::enum_devices()
+            .into_iter()
+            .filter(|node| pred(&Candidate::new(node.index())))
+            .filter_map(|node| describe(node.index()))
+            .collect();
+        Ok(devices)
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        let mut cache = self.cache.take().unwrap_or_default();
         let nodes = context::enum_devices()
             .into_iter()
-            .filter_map(|dev| {
-                let index = dev.index();
-                let dev = match Handle::new(index) {
-                    Ok(dev) => dev,
-                    Err(_) => return None,
-                };
-
-                let caps = match dev.inner().query_caps() {
-                    Ok(caps) => caps,
-                    Err(_) => return None,
-                };
-
-                // For now, require video capture and streaming capabilities.
-                // Very old devices may only support the read() I/O mechanism, so support for those
-                // might be added in the future. Every recent (released during the last ten to twenty
-                // years) webcam should support streaming though.
-                let capture_flag = v4l::capability::Flags::VIDEO_CAPTURE;
-                let streaming_flag = v4l::capability::Flags::STREAMING;
-                if caps.capabilities & capture_flag != capture_flag
-                    || caps.capabilities & streaming_flag != streaming_flag
-                {
-                    return None;
+            .map(|node| {
+                let index = node.index();
+                let ino = fs::metadata(node.path()).ok().map(|meta| meta.ino());
+
+                // Reuse the description if the node did not change.
+                let pos = cache
+                    .iter()
+                    .position(|node| node.index == index && node.ino.is_some() && node.ino == ino);
+                if let Some(pos) = pos {
+                    return cache.swap_remove(pos);
                 }
 
-                Some(device::Description {
-                    uri: format!("v4l:///dev/video{}", index),
-                    product: caps.card,
-                })
+                debug!(index, "querying new video node");
+                Node {
+                    index,
+                    ino,
+                    desc: describe(index),
+                }
             })
             .collect();
 
-        Ok(nodes)
+        self.cache = Some(nodes);
+        Ok(())
     }
 
     fn open_device<'a>(&self, uri: &str) -> Result<PlatformDevice<'a>> {
-        if uri.starts_with("v4l://") {
-            let handle = crate::platform::v4l2::device::Handle::with_uri(uri)?;
+        if self.native_only {
+            self.open_device_raw(uri)
+        } else if uri.starts_with("v4l://") {
+            let handle = Handle::with_uri(uri)?.with_memory(self.memory);
             Ok(PlatformDevice::V4l2(handle))
         } else {
             Err(Error::new(ErrorKind::Other, "invalid URI"))
         }
     }
+
+    fn estimate_bandwidth(
+        &self,
+        streams: &[(&str, &stream::Descriptor)],
+    ) -> device::BandwidthReport {
+        let streams: Vec<_> = streams
+            .iter()
+            .map(|(uri, desc)| (usb_bus(uri), *desc))
+            .collect();
+        device::BandwidthReport::new(&streams)
+    }
+}
+
+/// Video node whose properties are queried on demand
+struct Candidate {
+    index: usize,
+    uri: String,
+    caps: RefCell<Option<Option<v4l::capability::Capabilities>>>,
+}
+
+impl Candidate {
+    fn new(index: usize) -> Self {
+        Candidate {
+            index,
+            uri: format!("v4l:///dev/video{}", index),
+            caps: RefCell::new(None),
+        }
+    }
+
+    /// Opens the node to query its capabilities, unless that was done before
+    fn query_caps<T>(&self, f: impl FnOnce(&v4l::capability::Capabilities) -> T) -> Option<T> {
+        let mut caps = self.caps.borrow_mut();
+        let caps = caps.get_or_insert_with(|| {
+            debug!(index = self.index, "querying video node");
+            Handle::new(self.index).ok()?.inner().query_caps().ok()
+        });
+        caps.as_ref().map(f)
+    }
+}
+
+impl device::Info for Candidate {
+    fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    fn product(&self) -> String {
+        // sysfs knows the name without opening the node
+        let sysfs = format!("/sys/class/video4linux/video{}/name", self.index);
+        if let Ok(name) = fs::read_to_string(sysfs) {
+            return clean_name(name.as_bytes());
+        }
+
+        self.query_caps(|caps| clean_name(caps.card.as_bytes()))
+            .unwrap_or_default()
+    }
+
+    fn bus_path(&self) -> Option<String> {
+        bus_path(self.index)
+    }
+
+    fn capabilities(&self) -> device::Capabilities {
+        self.query_caps(|caps| capabilities(caps.capabilities))
+            .unwrap_or(device::Capabilities::NONE)
+    }
+}
+
+/// Returns the sysfs path of the hardware behind a video node, e.g.
+/// `/devices/pci0000:00/0000:00:14.0/usb1/1-2/1-2:1.0`
+fn bus_path(index: usize) -> Option<String> {
+    let sysfs = format!("/sys/class/video4linux/video{}/device", index);
+    let dev = fs::canonicalize(sysfs).ok()?;
+    let path = dev.strip_prefix("/sys").ok()?;
+    Some(format!("/{}", path.to_str()?))
+}
+
+/// Looks up the USB bus of a video node in sysfs, returns `None` for non-USB devices
+fn usb_bus(uri: &str) -> Option<device::UsbBus> {
+    // resolve symlinks such as /dev/v4l/by-id/* to the actual node
+    let node = fs::canonicalize(uri.strip_prefix("v4l://")?).ok()?;
+    let sysfs = Path::new("/sys/class/video4linux")
+        .join(node.file_name()?)
+        .join("device");
+    // The link points to the USB interface, the bus attributes belong to the USB device above.
+    let sysfs = fs::canonicalize(sysfs).ok()?;
+    let dir = sysfs.ancestors().find(|dir| dir.join("busnum").exists())?;
+
+    let attr = |name: &str| -> Option<String> {
+        Some(fs::read_to_string(dir.join(name)).ok()?.trim().to_string())
+    };
+    Some(device::UsbBus {
+        number: attr("busnum")?.parse().ok()?,
+        // low speed devices report "1.5", round it down so it is not mistaken for an unknown
+        // (i.e. high speed) link
+        speed: attr("speed")
+            .and_then(|speed| speed.parse::<f64>().ok())
+            .map(|speed| speed as u32),
+    })
+}
+
+/// Returns the description of a video node, or `None` if it is not a capture device
+fn describe(index: usize) -> Option<device::Description> {
+    let dev = match Handle::new(index) {
+        Ok(dev) => dev,
+        Err(_) => return None,
+    };
+
+    let caps = match dev.inner().query_caps() {
+        Ok(caps) => caps,
+        Err(_) => return None,
+    };
+
+    // Require video capture and either streaming or read() I/O. Every recent (released during
+    // the last ten to twenty years) webcam supports streaming, but some simple devices (e.g.
+    // capture dongles or virtual devices) only support read().
+    let flags = caps.capabilities;
+    if !flags.contains(v4l::capability::Flags::VIDEO_CAPTURE)
+        || !flags.intersects(v4l::capability::Flags::STREAMING | v4l::capability::Flags::READ_WRITE)
+    {
+        return None;
+    }
+
+    // Most drivers allow multiple opens, probe it while we hold the device open anyways.
+    let exclusive = match Handle::new(index) {
+        Ok(_) => false,
+        Err(e) => e.raw_os_error() == Some(libc::EBUSY),
+    };
+
+    Some(device::Description {
+        uri: format!("v4l:///dev/video{}", index),
+        product: clean_name(caps.card.as_bytes()),
+        exclusive,
+        bus_path: bus_path(index),
+    })
 }
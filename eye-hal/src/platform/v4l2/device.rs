@@ -1,29 +1,149 @@
-use std::{convert::TryInto, io, path::Path, time::Duration};
+use std::{
+    convert::TryInto,
+    fs::{self, OpenOptions},
+    io, mem,
+    os::unix::{
+        fs::OpenOptionsExt,
+        io::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
+    },
+    path::Path,
+    time::Duration,
+};
 
+use v4l::buffer::Type as BufType;
+use v4l::capability::Flags as CapFlags;
 use v4l::control::{
     Control, MenuItem as ControlMenuItem, Type as ControlType, Value as ControlValue,
 };
+use v4l::device::Handle as CaptureHandle;
+use v4l::format::{FieldOrder as CaptureFieldOrder, Flags as FormatFlags};
+use v4l::v4l2;
+use v4l::v4l_sys::{
+    v4l2_cropcap, v4l2_rect, v4l2_selection, V4L2_CID_ANALOGUE_GAIN, V4L2_CID_AUTOGAIN,
+    V4L2_CID_AUTO_FOCUS_RANGE, V4L2_CID_AUTO_FOCUS_START, V4L2_CID_AUTO_FOCUS_STOP,
+    V4L2_CID_AUTO_WHITE_BALANCE, V4L2_CID_BACKLIGHT_COMPENSATION, V4L2_CID_BRIGHTNESS,
+    V4L2_CID_CONTRAST, V4L2_CID_DIGITAL_GAIN, V4L2_CID_EXPOSURE_ABSOLUTE, V4L2_CID_EXPOSURE_AUTO,
+    V4L2_CID_FOCUS_ABSOLUTE, V4L2_CID_FOCUS_AUTO, V4L2_CID_GAIN, V4L2_CID_GAMMA, V4L2_CID_HUE,
+    V4L2_CID_MIN_BUFFERS_FOR_CAPTURE, V4L2_CID_PAN_ABSOLUTE, V4L2_CID_POWER_LINE_FREQUENCY,
+    V4L2_CID_SATURATION, V4L2_CID_SHARPNESS, V4L2_CID_TILT_ABSOLUTE,
+    V4L2_CID_WHITE_BALANCE_TEMPERATURE, V4L2_CID_ZOOM_ABSOLUTE, V4L2_SEL_TGT_CROP,
+};
 use v4l::video::Capture;
 use v4l::Device as CaptureDevice;
 use v4l::Format as CaptureFormat;
 use v4l::FourCC as FourCC_;
 
-use crate::control;
-use crate::error::{Error, ErrorKind, Result};
-use crate::format::PixelFormat;
+use crate::control::{self, PowerLineFrequency};
+use crate::device;
+use crate::error::{Error, ErrorKind, FormatMismatch, Result};
+use crate::format::{ImageFormat, PixelFormat};
+use crate::geometry::{Fraction, Rect};
+use crate::platform::v4l2::capabilities;
+use crate::platform::v4l2::read::Handle as ReadHandle;
 use crate::platform::v4l2::stream::Handle as StreamHandle;
-use crate::platform::Stream as PlatformStream;
-use crate::stream;
+use crate::platform::{clean_name, Stream as PlatformStream};
+use crate::stream::{self, CacheHints, FieldOrder, MemoryType};
 use crate::traits::Device;
 
 pub struct Handle {
     inner: CaptureDevice,
+    /// Memory backing the buffers of streams which do not select one
+    memory: MemoryType,
+}
+
+/// `V4L2_EXPOSURE_MANUAL` of `enum v4l2_exposure_auto_type`
+const V4L2_EXPOSURE_MANUAL: i64 = 1;
+
+/// Automatic controls as (auto control, resolved value control, manual mode value)
+const AUTO_CONTROLS: [(u32, u32, i64); 3] = [
+    (
+        V4L2_CID_EXPOSURE_AUTO,
+        V4L2_CID_EXPOSURE_ABSOLUTE,
+        V4L2_EXPOSURE_MANUAL,
+    ),
+    (
+        V4L2_CID_AUTO_WHITE_BALANCE,
+        V4L2_CID_WHITE_BALANCE_TEMPERATURE,
+        0,
+    ),
+    (V4L2_CID_AUTOGAIN, V4L2_CID_GAIN, 0),
+];
+
+/// Argument of the uvcvideo extension unit ioctl, see `linux/uvcvideo.h`
+#[repr(C)]
+struct UvcXuControlQuery {
+    unit: u8,
+    selector: u8,
+    query: u8,
+    size: u16,
+    data: *mut u8,
+}
+
+/// `_IOWR('u', 0x21, struct uvc_xu_control_query)`
+const UVCIOC_CTRL_QUERY: v4l2::vidioc::_IOC_TYPE =
+    ((3 << 30) | (mem::size_of::<UvcXuControlQuery>() << 16) | ((b'u' as usize) << 8) | 0x21)
+        as v4l2::vidioc::_IOC_TYPE;
+
+/// `V4L2_CID_UVC_REGION_OF_INTEREST_RECT` and `V4L2_CID_UVC_REGION_OF_INTEREST_AUTO` of
+/// `linux/uvcvideo.h`, which map the UVC region of interest control
+const V4L2_CID_UVC_REGION_OF_INTEREST_RECT: u32 = 0x009a_1901;
+const V4L2_CID_UVC_REGION_OF_INTEREST_AUTO: u32 = 0x009a_1902;
+
+/// `struct v4l2_audio` of `linux/videodev2.h`
+#[repr(C)]
+struct V4l2Audio {
+    index: u32,
+    name: [u8; 32],
+    capability: u32,
+    mode: u32,
+    reserved: [u32; 2],
+}
+
+/// `V4L2_AUDCAP_STEREO`: the input is stereo
+const V4L2_AUDCAP_STEREO: u32 = 0x0001;
+
+/// `_IOWR('V', 65, struct v4l2_audio)`
+const VIDIOC_ENUMAUDIO: v4l2::vidioc::_IOC_TYPE =
+    ((3 << 30) | (mem::size_of::<V4l2Audio>() << 16) | ((b'V' as usize) << 8) | 65)
+        as v4l2::vidioc::_IOC_TYPE;
+
+/// `V4L2_CTRL_WHICH_*` values selecting the value read by `VIDIOC_G_EXT_CTRLS`
+const V4L2_CTRL_WHICH_DEF_VAL: u32 = 0x0f00_0000;
+const V4L2_CTRL_WHICH_MIN_VAL: u32 = 0x0f02_0000;
+const V4L2_CTRL_WHICH_MAX_VAL: u32 = 0x0f03_0000;
+
+/// `struct v4l2_ext_control` of `linux/videodev2.h` for scalar controls
+#[repr(C, packed)]
+pub(crate) struct ExtControl {
+    pub id: u32,
+    pub size: u32,
+    pub reserved: u32,
+    pub value: i64,
+}
+
+/// `struct v4l2_ext_controls` of `linux/videodev2.h`
+#[repr(C)]
+pub(crate) struct ExtControls {
+    pub which: u32,
+    pub count: u32,
+    pub error_idx: u32,
+    pub request_fd: i32,
+    pub reserved: u32,
+    pub controls: *mut ExtControl,
 }
 
 impl Handle {
     pub fn new(index: usize) -> io::Result<Self> {
-        let dev = Handle {
-            inner: CaptureDevice::new(index)?,
+        debug!(index, "opening device");
+        let dev = match CaptureDevice::new(index) {
+            Ok(dev) => Handle {
+                inner: dev,
+                memory: MemoryType::default(),
+            },
+            Err(e) => {
+                error!(index, error = %e, "failed to open device");
+                return Err(e);
+            }
         };
         Ok(dev)
     }
@@ -39,15 +159,385 @@ impl Handle {
     }
 
     pub fn with_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let dev = Handle {
-            inner: CaptureDevice::with_path(path)?,
+        let path = path.as_ref();
+        debug!(path = %path.display(), "opening device");
+        let dev = match CaptureDevice::with_path(path) {
+            Ok(dev) => Handle {
+                inner: dev,
+                memory: MemoryType::default(),
+            },
+            Err(e) => {
+                error!(path = %path.display(), error = %e, "failed to open device");
+                return Err(e);
+            }
         };
         Ok(dev)
     }
 
+    /// Opens the device node directly, bypassing libv4l
+    ///
+    /// When the v4l crate is built with libv4l support, devices opened through [`Self::with_path`]
+    /// may advertise additional formats (e.g. RGB for cameras which only produce MJPEG or Bayer
+    /// data). These are converted in software by libv4l, which costs CPU time and adds latency.
+    /// Such formats are marked as emulated in the stream descriptors.
+    ///
+    /// Devices opened by this method only offer the formats natively produced by the hardware,
+    /// frames are delivered without any conversion.
+    pub fn with_path_raw<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        debug!(path = %path.display(), "opening raw device");
+        let file = match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)
+        {
+            Ok(file) => file,
+            Err(e) => {
+                error!(path = %path.display(), error = %e, "failed to open device");
+                return Err(e);
+            }
+        };
+
+        // libv4l passes through all calls on file descriptors it did not open itself.
+        Ok(unsafe { Self::from_raw_fd(file.into_raw_fd()) })
+    }
+
+    /// Opens the device node read-only, e.g. to inspect its controls and formats
+    ///
+    /// Querying controls and enumerating formats works on read-only handles, so this only needs
+    /// read permission on the device node. Opening a device never claims it, another process can
+    /// keep capturing from it in the meantime. Starting a stream fails, since the buffers cannot
+    /// be mapped writable. Like [`Self::with_path_raw`], this bypasses libv4l.
+    pub fn with_path_read_only<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        debug!(path = %path.display(), "opening read-only device");
+        let file = match OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)
+        {
+            Ok(file) => file,
+            Err(e) => {
+                error!(path = %path.display(), error = %e, "failed to open device");
+                return Err(e);
+            }
+        };
+
+        Ok(unsafe { Self::from_raw_fd(file.into_raw_fd()) })
+    }
+
+    /// Opens a second handle to the same device node
+    ///
+    /// The clone has a file descriptor of its own, so it can be moved to another thread while
+    /// this handle keeps streaming. It is meant for control access: reading and writing
+    /// controls, querying formats and capabilities are safe at any time. Operations which
+    /// change the stream configuration (formats, frame intervals, buffers) fail with
+    /// [`ErrorKind::DeviceBusy`] while a stream of another handle is running, since the driver
+    /// grants them to the handle which owns the stream only.
+    ///
+    /// The node is reopened through `/proc/self/fd`, which bypasses libv4l like
+    /// [`Self::with_path_raw`], so controls emulated by libv4l are not available on the clone.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let fd = self.as_raw_fd();
+        debug!(fd, "reopening device");
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(format!("/proc/self/fd/{}", fd))?;
+
+        Ok(unsafe { Self::from_raw_fd(file.into_raw_fd()) }.with_memory(self.memory))
+    }
+
+    /// Selects the memory backing the buffers of streams which do not select one themselves
+    ///
+    /// See [`DeviceStreamSettings::with_memory`](stream::DeviceStreamSettings::with_memory).
+    pub fn with_memory(mut self, memory: MemoryType) -> Self {
+        self.memory = memory;
+        self
+    }
+
     pub fn inner(&self) -> &CaptureDevice {
         &self.inner
     }
+
+    /// Returns a stream which produces metadata buffers
+    ///
+    /// Some drivers (e.g. uvcvideo) expose per-frame metadata such as the applied exposure and
+    /// gain on a separate device node. Open that node and use this method to read the metadata in
+    /// parallel to the video stream of the main node. The buffers carry the raw metadata as
+    /// produced by the driver, their sequence numbers can be used to match them to video buffers.
+    pub fn start_meta_stream<'a>(&self, buffers_count: Option<usize>) -> Result<StreamHandle<'a>> {
+        let caps = self.inner.query_caps()?;
+        if !caps.capabilities.contains(CapFlags::META_CAPTURE) {
+            return Err(Error::new(
+                ErrorKind::NotSupported,
+                "device does not support metadata capture",
+            ));
+        }
+
+        StreamHandle::with_buffers(
+            self,
+            BufType::MetaCapture,
+            buffers_count.unwrap_or(4) as u32,
+            CacheHints::NONE,
+            MemoryType::Mmap,
+        )
+    }
+
+    /// Snaps a frame size to the step grid of the device
+    ///
+    /// Devices with stepwise frame sizes only accept sizes which are a multiple of the step size
+    /// apart from the minimum size, otherwise the driver picks some other size. Discrete sizes are
+    /// returned unchanged.
+    fn snap_size(&self, fourcc: FourCC_, width: u32, height: u32) -> Result<(u32, u32)> {
+        for framesize in self.inner.enum_framesizes(fourcc)? {
+            if let v4l::framesize::FrameSizeEnum::Stepwise(size) = framesize.size {
+                let snapped = (
+                    snap(width, size.min_width, size.max_width, size.step_width),
+                    snap(height, size.min_height, size.max_height, size.step_height),
+                );
+                if snapped != (width, height) {
+                    debug!(
+                        width,
+                        height,
+                        snapped_width = snapped.0,
+                        snapped_height = snapped.1,
+                        "snapped frame size to step grid"
+                    );
+                }
+                return Ok(snapped);
+            }
+        }
+
+        Ok((width, height))
+    }
+
+    /// Returns the interval offered for a frame size which matches the requested one
+    ///
+    /// Descriptors store intervals as durations, so an offered interval is accepted if it is
+    /// within 1% of the requested one. The closest one wins, which tells NTSC rates apart from
+    /// their integer counterparts (e.g. 59.94 and 60 fps).
+    fn frame_interval(
+        &self,
+        fourcc: FourCC_,
+        width: u32,
+        height: u32,
+        interval: Duration,
+    ) -> Result<v4l::Fraction> {
+        let requested = || {
+            let fraction = Fraction::from(interval);
+            v4l::Fraction::new(fraction.numerator, fraction.denominator)
+        };
+        let secs = |fraction: v4l::Fraction| {
+            fraction.numerator as f64 / fraction.denominator.max(1) as f64
+        };
+
+        let intervals = match self.inner.enum_frameintervals(fourcc, width, height) {
+            Ok(intervals) if !intervals.is_empty() => intervals,
+            // Not all drivers enumerate intervals, let them pick the closest one.
+            _ => return Ok(requested()),
+        };
+
+        let target = interval.as_secs_f64();
+        let mut best: Option<(f64, v4l::Fraction)> = None;
+        for frameinterval in intervals {
+            let candidate = match frameinterval.interval {
+                v4l::frameinterval::FrameIntervalEnum::Discrete(fraction) => fraction,
+                v4l::frameinterval::FrameIntervalEnum::Stepwise(range) => {
+                    if target < secs(range.min) || target > secs(range.max) {
+                        continue;
+                    }
+                    requested()
+                }
+            };
+
+            let error = (secs(candidate) - target).abs();
+            if !matches!(best, Some((best, _)) if best <= error) {
+                best = Some((error, candidate));
+            }
+        }
+
+        match best {
+            Some((error, fraction)) if error <= target * 0.01 => Ok(fraction),
+            _ => Err(Error::new(
+                ErrorKind::UnsupportedInterval,
+                format!(
+                    "frame interval {:?} is not offered for {}x{}",
+                    interval, width, height
+                ),
+            )),
+        }
+    }
+
+    /// Returns the minimum number of buffers required by the driver
+    fn min_buffers(&self) -> usize {
+        match self.inner.control(V4L2_CID_MIN_BUFFERS_FOR_CAPTURE) {
+            Ok(Control {
+                value: ControlValue::Integer(min),
+                ..
+            }) => min.max(0) as usize,
+            _ => 1,
+        }
+    }
+
+    /// Reads a single scalar control value using `VIDIOC_G_EXT_CTRLS`
+    fn ext_control(&self, id: u32, which: u32) -> io::Result<i64> {
+        let mut ctrl = ExtControl {
+            id,
+            size: 0,
+            reserved: 0,
+            value: 0,
+        };
+        let mut ctrls = ExtControls {
+            which,
+            count: 1,
+            error_idx: 0,
+            request_fd: 0,
+            reserved: 0,
+            controls: &mut ctrl,
+        };
+        unsafe {
+            v4l2::ioctl(
+                self.inner.handle().fd(),
+                v4l2::vidioc::VIDIOC_G_EXT_CTRLS,
+                &mut ctrls as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        Ok(ctrl.value)
+    }
+
+    /// Issues a selection ioctl, mapping the errors of drivers which cannot crop
+    fn selection(&self, request: v4l2::vidioc::_IOC_TYPE, sel: &mut v4l2_selection) -> Result<()> {
+        let res = unsafe {
+            v4l2::ioctl(
+                self.inner.handle().fd(),
+                request,
+                sel as *mut _ as *mut std::os::raw::c_void,
+            )
+        };
+
+        match res {
+            Ok(()) => Ok(()),
+            Err(e)
+                if matches!(
+                    e.raw_os_error(),
+                    Some(libc::ENOTTY | libc::EINVAL | libc::ENODATA)
+                ) =>
+            {
+                Err(Error::new(ErrorKind::NotSupported, "device cannot crop"))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Looks up the sound card which belongs to the same hardware as the video node in sysfs
+    fn alsa_device(&self) -> Option<String> {
+        let node = fs::read_link(format!("/proc/self/fd/{}", self.inner.handle().fd())).ok()?;
+        let sysfs = Path::new("/sys/class/video4linux")
+            .join(node.file_name()?)
+            .join("device");
+        let dev = fs::canonicalize(sysfs).ok()?;
+
+        // USB devices expose audio on a separate interface, so look at the other interfaces of
+        // the USB device as well. Other devices (e.g. PCI cards) register the card themselves.
+        let mut dirs = vec![dev.clone()];
+        if dev.join("bInterfaceNumber").exists() {
+            if let Some(usb_dev) = dev.parent() {
+                dirs.extend(
+                    fs::read_dir(usb_dev)
+                        .ok()?
+                        .filter_map(|entry| entry.ok())
+                        .map(|entry| entry.path())
+                        .filter(|path| path.join("bInterfaceNumber").exists()),
+                );
+            }
+        }
+
+        dirs.iter()
+            .filter_map(|dir| fs::read_dir(dir.join("sound")).ok())
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("card"))
+            .find_map(|entry| fs::read_to_string(entry.path().join("id")).ok())
+            .map(|id| format!("hw:CARD={}", id.trim()))
+    }
+
+    /// Returns the first of the given controls the device has
+    fn find_control(&self, ids: &[u32]) -> Result<Option<v4l::control::Description>> {
+        let mut controls = self.inner.query_controls()?;
+        for id in ids {
+            if let Some(pos) = controls.iter().position(|desc| desc.id == *id) {
+                return Ok(Some(controls.swap_remove(pos)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn set_gain(&mut self, ids: &[u32], value: f64) -> Result<()> {
+        let desc = self.find_control(ids)?.ok_or_else(|| {
+            Error::new(ErrorKind::NotSupported, "device has no such gain control")
+        })?;
+        debug!(id = desc.id, value, "setting gain");
+        self.inner.set_control(Control {
+            id: desc.id,
+            value: ControlValue::Integer(value as i64),
+        })?;
+
+        Ok(())
+    }
+
+    /// Switches the continuous automatic focus on or off
+    fn set_focus_auto(&mut self, on: bool) -> Result<()> {
+        debug!(on, "switching automatic focus");
+        self.inner.set_control(Control {
+            id: V4L2_CID_FOCUS_AUTO,
+            value: ControlValue::Boolean(on),
+        })?;
+        Ok(())
+    }
+
+    fn power_line_control(&self) -> Result<v4l::control::Description> {
+        self.inner
+            .query_controls()?
+            .into_iter()
+            .find(|desc| desc.id == V4L2_CID_POWER_LINE_FREQUENCY)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotSupported,
+                    "device has no power line frequency control",
+                )
+            })
+    }
+}
+
+impl FromRawFd for Handle {
+    /// Wraps an already opened V4L2 device node.
+    ///
+    /// This is useful in sandboxed processes which cannot open `/dev/videoN` themselves, but
+    /// receive the file descriptor from a broker (e.g. a desktop portal or systemd socket
+    /// activation).
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `fd` is an open file descriptor referring to a V4L2 device.
+    /// Ownership of the descriptor is transferred to the handle, which closes it when dropped.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        debug!(fd, "wrapping device file descriptor");
+        Handle {
+            inner: CaptureDevice::from(CaptureHandle::from(fd)),
+            memory: MemoryType::default(),
+        }
+    }
+}
+
+impl AsRawFd for Handle {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.handle().fd()
+    }
 }
 
 impl<'a> Device<'a> for Handle {
@@ -71,9 +561,9 @@ impl<'a> Device<'a> for Handle {
                                 width: size.width,
                                 height: size.height,
                                 pixfmt: PixelFormat::from(&format.fourcc.repr),
-                                interval: Duration::from_secs_f64(
-                                    fraction.numerator as f64 / fraction.denominator as f64,
-                                ),
+                                interval: Fraction::new(fraction.numerator, fraction.denominator)
+                                    .into(),
+                                emulated: format.flags.contains(FormatFlags::EMULATED),
                             });
                         }
                     }
@@ -84,6 +574,34 @@ impl<'a> Device<'a> for Handle {
         Ok(streams)
     }
 
+    fn formats_at(&self, width: u32, height: u32) -> Result<Vec<PixelFormat>> {
+        let mut formats = Vec::new();
+        for format in self.inner.enum_formats()? {
+            // Unlike the stream list, this includes the sizes on the grid of stepwise formats.
+            let offered = self
+                .inner
+                .enum_framesizes(format.fourcc)?
+                .into_iter()
+                .any(|framesize| match framesize.size {
+                    v4l::framesize::FrameSizeEnum::Discrete(size) => {
+                        size.width == width && size.height == height
+                    }
+                    v4l::framesize::FrameSizeEnum::Stepwise(size) => {
+                        snap(width, size.min_width, size.max_width, size.step_width) == width
+                            && snap(height, size.min_height, size.max_height, size.step_height)
+                                == height
+                    }
+                });
+
+            let pixfmt = PixelFormat::from(&format.fourcc.repr);
+            if offered && !formats.contains(&pixfmt) {
+                formats.push(pixfmt);
+            }
+        }
+
+        Ok(formats)
+    }
+
     fn controls(&self) -> Result<Vec<control::Descriptor>> {
         let mut controls = Vec::new();
         let plat_controls = self.inner.query_controls()?;
@@ -100,13 +618,21 @@ impl<'a> Device<'a> for Handle {
                     step: control.step as f32,
                 },
                 ControlType::Boolean => control::Type::Boolean,
-                ControlType::Menu => {
+                ControlType::Menu | ControlType::IntegerMenu => {
                     let mut items = Vec::new();
+                    let mut default = None;
                     if let Some(plat_items) = control.items {
                         for plat_item in plat_items {
+                            // Drivers may skip indices, so the default index is not necessarily
+                            // the position of the item.
+                            if plat_item.0 as i64 == control.default {
+                                default = Some(items.len());
+                            }
                             match plat_item.1 {
                                 ControlMenuItem::Name(name) => {
-                                    items.push(control::MenuItem::String(name));
+                                    items.push(control::MenuItem::String(clean_name(
+                                        name.as_bytes(),
+                                    )));
                                 }
                                 ControlMenuItem::Value(value) => {
                                     items.push(control::MenuItem::Number(value as f64));
@@ -114,7 +640,7 @@ impl<'a> Device<'a> for Handle {
                             }
                         }
                     }
-                    control::Type::Menu(items)
+                    control::Type::Menu { items, default }
                 }
                 ControlType::Button => control::Type::Stateless,
                 ControlType::String => control::Type::String,
@@ -143,7 +669,7 @@ impl<'a> Device<'a> for Handle {
 
             controls.push(control::Descriptor {
                 id: control.id,
-                name: control.name,
+                name: clean_name(control.name.as_bytes()),
                 typ: state_type,
                 flags,
             })
@@ -164,6 +690,81 @@ impl<'a> Device<'a> for Handle {
         }
     }
 
+    fn control_which(&self, id: u32, which: control::Which) -> Result<control::State> {
+        let code = match which {
+            control::Which::Current => return self.control(id),
+            control::Which::Default => V4L2_CTRL_WHICH_DEF_VAL,
+            control::Which::Min => V4L2_CTRL_WHICH_MIN_VAL,
+            control::Which::Max => V4L2_CTRL_WHICH_MAX_VAL,
+        };
+        let desc = self
+            .find_control(&[id])?
+            .ok_or_else(|| Error::new(ErrorKind::NotSupported, "device has no such control"))?;
+
+        let value = match self.ext_control(id, code) {
+            // only 64-bit controls fill the whole union, the others store an i32
+            Ok(value) if desc.typ == ControlType::Integer64 => value,
+            Ok(value) => value as i32 as i64,
+            // Older kernels cannot read the limits this way, fall back to the values reported by
+            // VIDIOC_QUERYCTRL.
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => {
+                trace!(id, ?which, "falling back to the control description");
+                match which {
+                    control::Which::Min => desc.minimum,
+                    control::Which::Max => desc.maximum,
+                    _ => desc.default,
+                }
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        match desc.typ {
+            ControlType::Boolean => Ok(control::State::Boolean(value != 0)),
+            ControlType::Integer
+            | ControlType::Integer64
+            | ControlType::Menu
+            | ControlType::IntegerMenu => Ok(control::State::Number(value as f64)),
+            _ => Err(Error::new(
+                ErrorKind::Other,
+                "control value cannot be mapped",
+            )),
+        }
+    }
+
+    fn control_id(&self, id: control::ControlId) -> Result<u32> {
+        use control::ControlId;
+
+        // candidates in order of preference
+        let ids: &[u32] = match id {
+            ControlId::Brightness => &[V4L2_CID_BRIGHTNESS],
+            ControlId::Contrast => &[V4L2_CID_CONTRAST],
+            ControlId::Saturation => &[V4L2_CID_SATURATION],
+            ControlId::Hue => &[V4L2_CID_HUE],
+            ControlId::Gamma => &[V4L2_CID_GAMMA],
+            ControlId::Sharpness => &[V4L2_CID_SHARPNESS],
+            ControlId::BacklightCompensation => &[V4L2_CID_BACKLIGHT_COMPENSATION],
+            ControlId::Exposure => &[V4L2_CID_EXPOSURE_ABSOLUTE],
+            ControlId::AutoExposure => &[V4L2_CID_EXPOSURE_AUTO],
+            ControlId::Gain => &[V4L2_CID_GAIN, V4L2_CID_ANALOGUE_GAIN],
+            ControlId::WhiteBalance => &[V4L2_CID_WHITE_BALANCE_TEMPERATURE],
+            ControlId::AutoWhiteBalance => &[V4L2_CID_AUTO_WHITE_BALANCE],
+            ControlId::Focus => &[V4L2_CID_FOCUS_ABSOLUTE],
+            ControlId::AutoFocus => &[V4L2_CID_FOCUS_AUTO],
+            ControlId::Zoom => &[V4L2_CID_ZOOM_ABSOLUTE],
+            ControlId::Pan => &[V4L2_CID_PAN_ABSOLUTE],
+            ControlId::Tilt => &[V4L2_CID_TILT_ABSOLUTE],
+            ControlId::Raw(id) => return Ok(id),
+        };
+
+        match self.find_control(ids)? {
+            Some(desc) => Ok(desc.id),
+            None => Err(Error::new(
+                ErrorKind::NotSupported,
+                format!("device has no {:?} control", id),
+            )),
+        }
+    }
+
     fn set_control(&mut self, id: u32, val: &control::State) -> Result<()> {
         let value = match val {
             control::State::Number(val) => ControlValue::Integer(*val as i64),
@@ -182,6 +783,10 @@ impl<'a> Device<'a> for Handle {
         Ok(())
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self, settings))
+    )]
     fn start_stream(
         &self,
         settings: stream::DeviceStreamSettings<'_>,
@@ -196,16 +801,682 @@ impl<'a> Device<'a> for Handle {
             ));
         };
         // configure frame format
-        let format = CaptureFormat::new(desc.width, desc.height, FourCC_::new(&fourcc));
-        self.inner.set_format(&format)?;
+        let (width, height) = self.snap_size(FourCC_::new(&fourcc), desc.width, desc.height)?;
+        let mut format = CaptureFormat::new(width, height, FourCC_::new(&fourcc));
+        if let Some(order) = settings.field_order {
+            format.field_order = field_order(order);
+        }
+        debug!(
+            width = format.width,
+            height = format.height,
+            fourcc = %format.fourcc,
+            "requesting format"
+        );
+        let format = self.inner.set_format(&format)?;
+        debug!(
+            width = format.width,
+            height = format.height,
+            fourcc = %format.fourcc,
+            "negotiated format"
+        );
+        // Most drivers adjust an unsupported format to the nearest one instead of rejecting it.
+        let pixfmt = PixelFormat::from(&format.fourcc.repr);
+        if (format.width, format.height) != (width, height)
+            || format.fourcc != FourCC_::new(&fourcc)
+        {
+            return Err(FormatMismatch {
+                requested: desc.clone(),
+                got: stream::Descriptor {
+                    width: format.width,
+                    height: format.height,
+                    pixfmt,
+                    interval: desc.interval,
+                    emulated: desc.emulated,
+                },
+            }
+            .into());
+        }
 
         // configure frame timing
-        let fps = (1.0 / desc.interval.as_secs_f32()) as u32;
+        let interval = self.frame_interval(
+            FourCC_::new(&fourcc),
+            format.width,
+            format.height,
+            desc.interval,
+        )?;
         let mut params = self.inner.params()?;
-        params.interval = v4l::Fraction::new(1, fps);
-        self.inner.set_params(&params)?;
+        params.interval = interval;
+        debug!(
+            numerator = interval.numerator,
+            denominator = interval.denominator,
+            "requesting frame interval"
+        );
+        let params = self.inner.set_params(&params)?;
+
+        let effective = stream::Descriptor {
+            width: format.width,
+            height: format.height,
+            pixfmt,
+            interval: Fraction::new(params.interval.numerator, params.interval.denominator).into(),
+            emulated: desc.emulated,
+        };
+
+        // Devices without streaming I/O can only be read from like a file.
+        let caps = self.inner.query_caps()?.capabilities;
+        if !caps.contains(CapFlags::STREAMING) && caps.contains(CapFlags::READ_WRITE) {
+            debug!("device does not support streaming, reading frames instead");
+            let handle = ReadHandle::new(self)?
+                .with_clock(settings.clock)
+                .with_timeout(settings.timeout)
+                .with_warmup_frames(settings.warmup_frames)
+                .with_descriptor(effective);
+            return Ok(PlatformStream::Custom(Box::new(handle)));
+        }
 
-        let handle = StreamHandle::with_buffers(self, settings.buffers_count.unwrap_or(4) as u32)?;
+        let buffers = match (settings.buffers_count, settings.latency) {
+            (Some(count), _) => count,
+            (None, Some(latency)) => {
+                stream::buffers_for_latency(effective.fps(), latency, self.min_buffers())
+            }
+            (None, None) => 4,
+        };
+        debug!(buffers, "requesting buffers");
+        let handle = StreamHandle::with_buffers(
+            self,
+            BufType::VideoCapture,
+            buffers as u32,
+            settings.cache_hints,
+            settings.memory.unwrap_or(self.memory),
+        )?
+        .with_clock(settings.clock)
+        .with_corrupt_frames(settings.corrupt_frames)
+        .with_consume_policy(settings.consume_policy)
+        .with_jpeg_trimming(settings.trim_jpeg)
+        .with_timeout(settings.timeout)
+        .with_warmup_frames(settings.warmup_frames)
+        .with_descriptor(effective);
         Ok(PlatformStream::V4l2(handle))
     }
+
+    fn power_line_frequency(&self) -> Result<PowerLineFrequency> {
+        let desc = self.power_line_control()?;
+        let value = match self.inner.control(desc.id)?.value {
+            ControlValue::Integer(value) => value,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    "control value cannot be mapped",
+                ))
+            }
+        };
+
+        let freq = match desc.typ {
+            ControlType::Menu => {
+                let name = desc
+                    .items
+                    .iter()
+                    .flatten()
+                    .find_map(|(index, item)| match item {
+                        ControlMenuItem::Name(name) if *index as i64 == value => Some(name),
+                        _ => None,
+                    });
+                name.and_then(|name| menu_frequency(name))
+                    .or_else(|| power_line_frequency(value))
+            }
+            ControlType::Integer if desc.maximum >= 60 => match value {
+                0 => Some(PowerLineFrequency::Disabled),
+                50 => Some(PowerLineFrequency::Hz50),
+                60 => Some(PowerLineFrequency::Hz60),
+                _ => None,
+            },
+            _ => power_line_frequency(value),
+        };
+
+        freq.ok_or_else(|| {
+            Error::new(
+                ErrorKind::Other,
+                format!("unknown power line frequency value: {}", value),
+            )
+        })
+    }
+
+    fn set_power_line_frequency(&mut self, freq: PowerLineFrequency) -> Result<()> {
+        let desc = self.power_line_control()?;
+        let value = match desc.typ {
+            ControlType::Menu => {
+                // Drivers do not always order the items as specified, so look at the names first
+                // and only fall back to the specified index for items with unknown names.
+                let items = desc.items.unwrap_or_default();
+                let by_name = items.iter().find(|(_, item)| match item {
+                    ControlMenuItem::Name(name) => menu_frequency(name) == Some(freq),
+                    _ => false,
+                });
+                let by_index = || {
+                    items.iter().find(|(index, item)| {
+                        *index as i64 == power_line_value(freq)
+                            && match item {
+                                ControlMenuItem::Name(name) => menu_frequency(name).is_none(),
+                                _ => true,
+                            }
+                    })
+                };
+                by_name.or_else(by_index).map(|(index, _)| *index as i64)
+            }
+            ControlType::Integer => {
+                // Some cameras report the frequency in Hertz instead of the specified values.
+                let value = if desc.maximum >= 60 {
+                    match freq {
+                        PowerLineFrequency::Disabled => Some(0),
+                        PowerLineFrequency::Hz50 => Some(50),
+                        PowerLineFrequency::Hz60 => Some(60),
+                        PowerLineFrequency::Auto => None,
+                    }
+                } else {
+                    Some(power_line_value(freq))
+                };
+                value.filter(|value| (desc.minimum..=desc.maximum).contains(value))
+            }
+            _ => None,
+        };
+
+        let value = value.ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotSupported,
+                format!("power line frequency {:?} is not supported", freq),
+            )
+        })?;
+        debug!(?freq, value, "setting power line frequency");
+        self.inner.set_control(Control {
+            id: desc.id,
+            value: ControlValue::Integer(value),
+        })?;
+
+        Ok(())
+    }
+
+    fn set_focus_mode(&mut self, mode: control::FocusMode) -> Result<()> {
+        let controls = self.inner.query_controls()?;
+        let has = |id| controls.iter().any(|desc| desc.id == id);
+
+        match mode {
+            control::FocusMode::Manual => {
+                if has(V4L2_CID_FOCUS_AUTO) {
+                    self.set_focus_auto(false)?;
+                } else if !has(V4L2_CID_FOCUS_ABSOLUTE) {
+                    return Err(Error::new(
+                        ErrorKind::NotSupported,
+                        "device has no focus control",
+                    ));
+                }
+                // abort a single focus run which may still be going on
+                if has(V4L2_CID_AUTO_FOCUS_STOP) {
+                    self.inner.set_control(Control {
+                        id: V4L2_CID_AUTO_FOCUS_STOP,
+                        value: ControlValue::None,
+                    })?;
+                }
+            }
+            control::FocusMode::Auto => {
+                if !has(V4L2_CID_AUTO_FOCUS_START) {
+                    return Err(Error::new(
+                        ErrorKind::NotSupported,
+                        "device does not support single focus runs",
+                    ));
+                }
+                // Drivers ignore the start button while continuous focus is on.
+                if has(V4L2_CID_FOCUS_AUTO) {
+                    self.set_focus_auto(false)?;
+                }
+                debug!("starting focus run");
+                self.inner.set_control(Control {
+                    id: V4L2_CID_AUTO_FOCUS_START,
+                    value: ControlValue::None,
+                })?;
+            }
+            control::FocusMode::Continuous => {
+                if !has(V4L2_CID_FOCUS_AUTO) {
+                    return Err(Error::new(
+                        ErrorKind::NotSupported,
+                        "device has no automatic focus control",
+                    ));
+                }
+                self.set_focus_auto(true)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set_focus_absolute(&mut self, value: i64) -> Result<()> {
+        let controls = self.inner.query_controls()?;
+        let desc = controls
+            .iter()
+            .find(|desc| desc.id == V4L2_CID_FOCUS_ABSOLUTE)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotSupported,
+                    "device has no absolute focus control",
+                )
+            })?;
+        let value = value.clamp(desc.minimum, desc.maximum.max(desc.minimum));
+
+        // The position control is inactive while automatic focus is on and drivers reject
+        // writes to inactive controls.
+        if controls.iter().any(|desc| desc.id == V4L2_CID_FOCUS_AUTO) {
+            self.set_focus_auto(false)?;
+        }
+        debug!(value, "setting focus position");
+        self.inner.set_control(Control {
+            id: V4L2_CID_FOCUS_ABSOLUTE,
+            value: ControlValue::Integer(value),
+        })?;
+
+        Ok(())
+    }
+
+    fn focus_distance(&self) -> Result<i64> {
+        if self.find_control(&[V4L2_CID_FOCUS_ABSOLUTE])?.is_none() {
+            return Err(Error::new(
+                ErrorKind::NotSupported,
+                "device has no absolute focus control",
+            ));
+        }
+
+        // The control is inactive under automatic focus, but drivers still report the position.
+        let value = self.inner.control(V4L2_CID_FOCUS_ABSOLUTE)?.value;
+        integer_value(&value)
+            .ok_or_else(|| Error::new(ErrorKind::Other, "control value cannot be mapped"))
+    }
+
+    fn set_focus_range(&mut self, range: control::FocusRange) -> Result<()> {
+        let desc = self
+            .find_control(&[V4L2_CID_AUTO_FOCUS_RANGE])?
+            .ok_or_else(|| {
+                Error::new(ErrorKind::NotSupported, "device has no focus range control")
+            })?;
+
+        let value = focus_range_value(range);
+        let offered = desc
+            .items
+            .iter()
+            .flatten()
+            .any(|(index, _)| *index as i64 == value);
+        if !offered {
+            return Err(Error::new(
+                ErrorKind::NotSupported,
+                format!("focus range {:?} is not supported", range),
+            ));
+        }
+
+        debug!(?range, "setting focus range");
+        self.inner.set_control(Control {
+            id: desc.id,
+            value: ControlValue::Integer(value),
+        })?;
+
+        Ok(())
+    }
+
+    fn current_format(&self) -> Result<ImageFormat> {
+        // VIDIOC_G_FMT only reads the format, unlike VIDIOC_S_FMT it does not need exclusive
+        // access to the device
+        let format = self.inner.format()?;
+        Ok(ImageFormat::new(
+            format.width,
+            format.height,
+            PixelFormat::from(&format.fourcc.repr),
+        )
+        .stride(format.stride as usize))
+    }
+
+    fn crop_caps(&self) -> Result<device::CropCaps> {
+        let mut caps: v4l2_cropcap = unsafe { mem::zeroed() };
+        caps.type_ = BufType::VideoCapture as u32;
+        let res = unsafe {
+            v4l2::ioctl(
+                self.inner.handle().fd(),
+                v4l2::vidioc::VIDIOC_CROPCAP,
+                &mut caps as *mut _ as *mut std::os::raw::c_void,
+            )
+        };
+
+        match res {
+            Ok(()) => {}
+            // Drivers without cropping support used to return EINVAL, newer ones return ENODATA.
+            Err(e)
+                if matches!(
+                    e.raw_os_error(),
+                    Some(libc::ENOTTY | libc::EINVAL | libc::ENODATA)
+                ) =>
+            {
+                return Err(Error::new(ErrorKind::NotSupported, "device cannot crop"))
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        let rect = |rect: v4l2_rect| Rect::new(rect.left, rect.top, rect.width, rect.height);
+        Ok(device::CropCaps {
+            bounds: rect(caps.bounds),
+            default: rect(caps.defrect),
+            pixel_aspect: (caps.pixelaspect.numerator, caps.pixelaspect.denominator),
+        })
+    }
+
+    fn crop(&self) -> Result<Rect> {
+        let mut sel: v4l2_selection = unsafe { mem::zeroed() };
+        sel.type_ = BufType::VideoCapture as u32;
+        sel.target = V4L2_SEL_TGT_CROP;
+        self.selection(v4l2::vidioc::VIDIOC_G_SELECTION, &mut sel)?;
+
+        Ok(Rect::new(sel.r.left, sel.r.top, sel.r.width, sel.r.height))
+    }
+
+    fn set_crop(&mut self, rect: Rect) -> Result<Rect> {
+        let mut sel: v4l2_selection = unsafe { mem::zeroed() };
+        sel.type_ = BufType::VideoCapture as u32;
+        sel.target = V4L2_SEL_TGT_CROP;
+        sel.r = v4l2_rect {
+            left: rect.left,
+            top: rect.top,
+            width: rect.width,
+            height: rect.height,
+        };
+        trace!(?rect, "setting crop rectangle");
+        self.selection(v4l2::vidioc::VIDIOC_S_SELECTION, &mut sel)?;
+
+        // the driver writes back the rectangle it chose
+        Ok(Rect::new(sel.r.left, sel.r.top, sel.r.width, sel.r.height))
+    }
+
+    fn gain_split_supported(&self) -> bool {
+        let controls = match self.inner.query_controls() {
+            Ok(controls) => controls,
+            Err(_) => return false,
+        };
+
+        [V4L2_CID_ANALOGUE_GAIN, V4L2_CID_DIGITAL_GAIN]
+            .iter()
+            .all(|id| controls.iter().any(|desc| desc.id == *id))
+    }
+
+    fn set_analog_gain(&mut self, value: f64) -> Result<()> {
+        self.set_gain(&[V4L2_CID_ANALOGUE_GAIN, V4L2_CID_GAIN], value)
+    }
+
+    fn set_digital_gain(&mut self, value: f64) -> Result<()> {
+        self.set_gain(&[V4L2_CID_DIGITAL_GAIN], value)
+    }
+
+    fn lock_auto_values(&mut self) -> Result<()> {
+        let controls = self.inner.query_controls()?;
+        let find = |id| controls.iter().find(|desc| desc.id == id);
+
+        for (auto, value, manual) in AUTO_CONTROLS.iter().copied() {
+            let auto = match (find(auto), find(value)) {
+                (Some(auto), Some(_)) => auto,
+                _ => continue,
+            };
+
+            let mode = integer_value(&self.inner.control(auto.id)?.value);
+            if mode == Some(manual) {
+                continue;
+            }
+
+            // The value control is inactive while the automatic mode is on, but drivers still
+            // report the resolved value.
+            let resolved = self.inner.control(value)?.value;
+
+            // Drivers reject writes to inactive controls, so switch to manual mode first. This
+            // clears the inactive flag, but may reset the value to the last manual one.
+            let mode = match auto.typ {
+                ControlType::Boolean => ControlValue::Boolean(manual != 0),
+                _ => ControlValue::Integer(manual),
+            };
+            debug!(id = auto.id, "switching control to manual mode");
+            self.inner.set_control(Control {
+                id: auto.id,
+                value: mode,
+            })?;
+            self.inner.set_control(Control {
+                id: value,
+                value: resolved,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn set_metering_roi(&mut self, rect: Rect, mode: control::MeteringMode) -> Result<()> {
+        let mut roi = v4l2_rect {
+            left: rect.left,
+            top: rect.top,
+            width: rect.width,
+            height: rect.height,
+        };
+        let mut ctrls = [
+            // The rectangle is a compound control, its value is a pointer to the payload.
+            ExtControl {
+                id: V4L2_CID_UVC_REGION_OF_INTEREST_RECT,
+                size: mem::size_of::<v4l2_rect>() as u32,
+                reserved: 0,
+                value: &mut roi as *mut v4l2_rect as usize as i64,
+            },
+            ExtControl {
+                id: V4L2_CID_UVC_REGION_OF_INTEREST_AUTO,
+                size: 0,
+                reserved: 0,
+                value: mode.bits() as i64,
+            },
+        ];
+        let mut ext = ExtControls {
+            which: 0,
+            count: ctrls.len() as u32,
+            error_idx: 0,
+            request_fd: 0,
+            reserved: 0,
+            controls: ctrls.as_mut_ptr(),
+        };
+        trace!(?rect, ?mode, "setting region of interest");
+        let res = unsafe {
+            v4l2::ioctl(
+                self.inner.handle().fd(),
+                v4l2::vidioc::VIDIOC_S_EXT_CTRLS,
+                &mut ext as *mut _ as *mut std::os::raw::c_void,
+            )
+        };
+
+        match res {
+            Ok(()) => Ok(()),
+            // older kernels and cameras without the UVC control do not know the control IDs
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => Err(Error::new(
+                ErrorKind::NotSupported,
+                "device has no region of interest control",
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn uvc_xu_query(
+        &mut self,
+        unit: u8,
+        selector: u8,
+        query: control::XuQuery,
+        data: &mut [u8],
+    ) -> Result<()> {
+        if data.len() > u16::MAX as usize {
+            return Err(Error::new(ErrorKind::Other, "control data too large"));
+        }
+
+        let mut xu = UvcXuControlQuery {
+            unit,
+            selector,
+            query: query.code(),
+            size: data.len() as u16,
+            data: data.as_mut_ptr(),
+        };
+        trace!(
+            unit,
+            selector,
+            ?query,
+            size = xu.size,
+            "querying extension unit"
+        );
+        let res = unsafe {
+            v4l2::ioctl(
+                self.inner.handle().fd(),
+                UVCIOC_CTRL_QUERY,
+                &mut xu as *mut _ as *mut std::os::raw::c_void,
+            )
+        };
+
+        match res {
+            Ok(()) => Ok(()),
+            // The device is not driven by uvcvideo or has no such unit or control.
+            Err(e) if e.raw_os_error() == Some(libc::ENOTTY) => {
+                Err(Error::new(ErrorKind::NotSupported, "not a UVC device"))
+            }
+            Err(e) if e.raw_os_error() == Some(libc::ENOENT) => Err(Error::new(
+                ErrorKind::NotSupported,
+                "no such extension unit control",
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn capabilities(&self) -> Result<device::Capabilities> {
+        let caps = self.inner.query_caps()?;
+        Ok(capabilities(caps.capabilities))
+    }
+
+    fn audio_inputs(&self) -> Result<Vec<device::AudioInput>> {
+        let mut inputs = Vec::new();
+        let mut alsa_device = None;
+
+        for index in 0.. {
+            let mut audio: V4l2Audio = unsafe { mem::zeroed() };
+            audio.index = index;
+            let res = unsafe {
+                v4l2::ioctl(
+                    self.inner.handle().fd(),
+                    VIDIOC_ENUMAUDIO,
+                    &mut audio as *mut _ as *mut std::os::raw::c_void,
+                )
+            };
+
+            match res {
+                Ok(()) => {}
+                // EINVAL marks the end of the list, devices without audio do not know the ioctl
+                Err(e) if matches!(e.raw_os_error(), Some(libc::EINVAL | libc::ENOTTY)) => break,
+                Err(e) => return Err(e.into()),
+            }
+
+            if index == 0 {
+                alsa_device = self.alsa_device();
+            }
+            inputs.push(device::AudioInput {
+                index: audio.index,
+                name: clean_name(&audio.name),
+                stereo: audio.capability & V4L2_AUDCAP_STEREO != 0,
+                alsa_device: alsa_device.clone(),
+            });
+        }
+
+        Ok(inputs)
+    }
+
+    fn log_status(&self) -> Result<()> {
+        let res = unsafe {
+            v4l2::ioctl(
+                self.inner.handle().fd(),
+                v4l2::vidioc::VIDIOC_LOG_STATUS,
+                std::ptr::null_mut(),
+            )
+        };
+
+        match res {
+            Ok(()) => Ok(()),
+            Err(e) if e.raw_os_error() == Some(libc::ENOTTY) => {
+                Err(Error::from(ErrorKind::NotSupported))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+fn field_order(order: FieldOrder) -> CaptureFieldOrder {
+    match order {
+        FieldOrder::Progressive => CaptureFieldOrder::Progressive,
+        FieldOrder::Top => CaptureFieldOrder::Top,
+        FieldOrder::Bottom => CaptureFieldOrder::Bottom,
+        FieldOrder::Interlaced => CaptureFieldOrder::Interlaced,
+        FieldOrder::InterlacedTopFirst => CaptureFieldOrder::InterlacedTB,
+        FieldOrder::InterlacedBottomFirst => CaptureFieldOrder::InterlacedBT,
+        FieldOrder::SequentialTopBottom => CaptureFieldOrder::SequentialTB,
+        FieldOrder::SequentialBottomTop => CaptureFieldOrder::SequentialBT,
+        FieldOrder::Alternate => CaptureFieldOrder::Alternate,
+    }
+}
+
+/// Returns the value of an integer or boolean control as integer
+fn integer_value(value: &ControlValue) -> Option<i64> {
+    match value {
+        ControlValue::Integer(value) => Some(*value),
+        ControlValue::Boolean(value) => Some(*value as i64),
+        _ => None,
+    }
+}
+
+/// Returns the control value specified by V4L2 for a power line frequency
+fn power_line_value(freq: PowerLineFrequency) -> i64 {
+    match freq {
+        PowerLineFrequency::Disabled => 0,
+        PowerLineFrequency::Hz50 => 1,
+        PowerLineFrequency::Hz60 => 2,
+        PowerLineFrequency::Auto => 3,
+    }
+}
+
+fn power_line_frequency(value: i64) -> Option<PowerLineFrequency> {
+    match value {
+        0 => Some(PowerLineFrequency::Disabled),
+        1 => Some(PowerLineFrequency::Hz50),
+        2 => Some(PowerLineFrequency::Hz60),
+        3 => Some(PowerLineFrequency::Auto),
+        _ => None,
+    }
+}
+
+/// Returns the menu index specified by V4L2 for an auto focus range
+fn focus_range_value(range: control::FocusRange) -> i64 {
+    match range {
+        control::FocusRange::Auto => 0,
+        control::FocusRange::Normal => 1,
+        control::FocusRange::Macro => 2,
+        control::FocusRange::Infinity => 3,
+    }
+}
+
+/// Interprets the name of a power line frequency menu item (e.g. "50 Hz")
+fn menu_frequency(name: &str) -> Option<PowerLineFrequency> {
+    let name = name.to_lowercase();
+    if name.contains("50") {
+        Some(PowerLineFrequency::Hz50)
+    } else if name.contains("60") {
+        Some(PowerLineFrequency::Hz60)
+    } else if name.contains("auto") {
+        Some(PowerLineFrequency::Auto)
+    } else if name.contains("disable") || name.contains("off") {
+        Some(PowerLineFrequency::Disabled)
+    } else {
+        None
+    }
+}
+
+/// Returns the value of `min + n * step` nearest to `value`, but at most `max`
+fn snap(value: u32, min: u32, max: u32, step: u32) -> u32 {
+    let step = step.max(1);
+    let value = value.clamp(min, max.max(min));
+    let steps = (value - min + step / 2) / step;
+    let max_steps = max.saturating_sub(min) / step;
+    min + steps.min(max_steps) * step
 }
@@ -7,13 +7,35 @@
 
 pub mod context;
 pub mod device;
+pub mod read;
 pub mod stream;
 
 pub use context::Context;
 
-use std::{convert::TryInto, str};
-
+use crate::device::Capabilities;
 use crate::format::PixelFormat;
+use std::convert::TryInto;
+
+/// Maps the capability flags reported by the driver
+pub(crate) fn capabilities(flags: v4l::capability::Flags) -> Capabilities {
+    use v4l::capability::Flags;
+
+    [
+        (Flags::VIDEO_CAPTURE, Capabilities::VIDEO_CAPTURE),
+        (Flags::STREAMING, Capabilities::STREAMING),
+        (Flags::READ_WRITE, Capabilities::READ_WRITE),
+        (Flags::META_CAPTURE, Capabilities::META_CAPTURE),
+        (
+            Flags::VIDEO_CAPTURE_MPLANE,
+            Capabilities::VIDEO_CAPTURE_MPLANE,
+        ),
+        (Flags::EXT_PIX_FORMAT, Capabilities::EXT_PIX_FORMAT),
+        (Flags::AUDIO, Capabilities::AUDIO),
+    ]
+    .iter()
+    .filter(|(flag, _)| flags.contains(*flag))
+    .fold(Capabilities::NONE, |acc, (_, cap)| acc | *cap)
+}
 
 impl From<&[u8; 4]> for PixelFormat {
     fn from(fourcc: &[u8; 4]) -> Self {
@@ -23,6 +45,10 @@ impl From<&[u8; 4]> for PixelFormat {
         // Mono (single-component) formats
         if fourcc == b"GREY" {
             PixelFormat::Gray(8)
+        } else if fourcc == b"Y10P" {
+            PixelFormat::Gray(10)
+        } else if fourcc == b"Y12P" {
+            PixelFormat::Gray(12)
         } else if fourcc == b"Y16 " {
             PixelFormat::Gray(16)
         } else if fourcc == b"Z16 " {
@@ -34,13 +60,23 @@ impl From<&[u8; 4]> for PixelFormat {
         } else if fourcc == b"RGB3" {
             PixelFormat::Rgb(24)
         }
+        // YUV formats
+        else if fourcc == b"YUYV" {
+            PixelFormat::Yuyv
+        } else if fourcc == b"NV12" {
+            PixelFormat::Nv12
+        } else if fourcc == b"YU12" {
+            PixelFormat::I420
+        } else if fourcc == b"YV12" {
+            PixelFormat::Yv12
+        }
         // Compressed formats
         else if fourcc == b"MJPG" {
             PixelFormat::Jpeg
         }
         // Misc
         else {
-            PixelFormat::Custom(String::from(str::from_utf8(fourcc).unwrap()))
+            PixelFormat::Fourcc(*fourcc)
         }
     }
 }
@@ -67,12 +103,19 @@ impl TryInto<[u8; 4]> for PixelFormat {
                     Err(())
                 }
             }
+            PixelFormat::Fourcc(repr) => Ok(repr),
             PixelFormat::Gray(8) => Ok(*b"GREY"),
+            PixelFormat::Gray(10) => Ok(*b"Y10P"),
+            PixelFormat::Gray(12) => Ok(*b"Y12P"),
             PixelFormat::Gray(16) => Ok(*b"Y16 "),
             PixelFormat::Depth(16) => Ok(*b"Z16 "),
             PixelFormat::Bgr(24) => Ok(*b"BGR3"),
             PixelFormat::Rgb(24) => Ok(*b"RGB3"),
             PixelFormat::Rgb(32) => Ok(*b"AB24"),
+            PixelFormat::Yuyv => Ok(*b"YUYV"),
+            PixelFormat::Nv12 => Ok(*b"NV12"),
+            PixelFormat::I420 => Ok(*b"YU12"),
+            PixelFormat::Yv12 => Ok(*b"YV12"),
             PixelFormat::Jpeg => Ok(*b"MJPG"),
             _ => Err(()),
         }
@@ -0,0 +1,234 @@
+use std::{io, os::unix::io::RawFd, sync::Arc, time::Duration};
+
+use v4l::device::Handle as CaptureHandle;
+use v4l::video::Capture;
+
+use crate::buffer::{Buffer, Clock, FrameType, Metadata as BufferMetadata, TimestampSource};
+use crate::error::{Error, ErrorKind, Result};
+use crate::format::PixelFormat;
+use crate::platform::v4l2::device::Handle as DeviceHandle;
+use crate::platform::v4l2::stream::{clock_id, convert, now};
+use crate::stream::Descriptor;
+use crate::traits::Stream;
+
+/// A stream reading frames from the device node like from a file
+///
+/// This is the fallback for simple devices (e.g. cheap capture dongles or virtual devices) which
+/// do not support streaming I/O. Every frame is copied from the driver into the buffer of the
+/// stream. The driver does not report any frame metadata, so frames are numbered by the stream
+/// and the capture time is taken once a frame was read.
+pub struct Handle {
+    /// Keeps the device open
+    _handle: Arc<CaptureHandle>,
+    fd: RawFd,
+    buf: Vec<u8>,
+    desc: Option<Descriptor>,
+    /// Sequence number of the next frame
+    sequence: u32,
+    /// Monotonic clock time at which the first frame was requested
+    start: Option<Duration>,
+    clock: Clock,
+    /// Maximum time to wait for a frame
+    timeout: Option<Duration>,
+    /// Number of frames to discard after the capture starts
+    warmup: usize,
+    /// Number of warmup frames which are still to be discarded
+    skip: usize,
+    paused: bool,
+}
+
+impl Handle {
+    /// Returns a stream for the format currently configured on the device
+    pub fn new(dev: &DeviceHandle) -> Result<Self> {
+        let handle = dev.inner().handle();
+        let fd = handle.fd();
+
+        // The driver reports the maximum size of a frame, reads never return more.
+        let format = dev.inner().format()?;
+        if format.size == 0 {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "driver did not report the frame size",
+            ));
+        }
+        debug!(size = format.size, "allocated read buffer");
+
+        Ok(Handle {
+            _handle: handle,
+            fd,
+            buf: vec![0; format.size as usize],
+            desc: None,
+            sequence: 0,
+            start: None,
+            clock: Clock::default(),
+            timeout: None,
+            warmup: 0,
+            skip: 0,
+            paused: false,
+        })
+    }
+
+    /// Selects the clock for the buffer capture time
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Limits the time to wait for a frame
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Discards a number of frames whenever the capture starts
+    pub fn with_warmup_frames(mut self, count: usize) -> Self {
+        self.warmup = count;
+        self
+    }
+
+    /// Sets the stream configuration negotiated with the driver
+    pub fn with_descriptor(mut self, desc: Descriptor) -> Self {
+        self.desc = Some(desc);
+        self
+    }
+
+    /// Waits until a frame can be read
+    fn wait(&self) -> io::Result<()> {
+        let mut pollfd = libc::pollfd {
+            fd: self.fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        let timeout = match self.timeout {
+            Some(timeout) => timeout.as_millis().min(i32::MAX as u128) as i32,
+            None => -1,
+        };
+        loop {
+            let ret = unsafe { libc::poll(&mut pollfd, 1, timeout) };
+            if ret == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "no frame arrived in time",
+                ));
+            }
+            if ret > 0 {
+                return Ok(());
+            }
+
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+
+    /// Reads the next frame into the buffer, returns its length
+    fn read(&mut self) -> io::Result<usize> {
+        loop {
+            // Waiting first also makes this work for devices which were opened in non-blocking
+            // mode.
+            self.wait()?;
+
+            let ret = unsafe {
+                libc::read(
+                    self.fd,
+                    self.buf.as_mut_ptr() as *mut libc::c_void,
+                    self.buf.len(),
+                )
+            };
+            if ret >= 0 {
+                return Ok(ret as usize);
+            }
+
+            let err = io::Error::last_os_error();
+            match err.kind() {
+                io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock => continue,
+                _ => return Err(err),
+            }
+        }
+    }
+}
+
+impl<'a> Stream<'a> for Handle {
+    type Item = Result<Buffer<'a>>;
+
+    fn next(&'a mut self) -> Option<Self::Item> {
+        if self.paused {
+            return None;
+        }
+        // The driver starts capturing on the first read.
+        let start = match self.start {
+            Some(start) => start,
+            None => {
+                self.skip = self.warmup;
+                *self.start.insert(now(libc::CLOCK_MONOTONIC))
+            }
+        };
+
+        let (len, sequence) = loop {
+            let len = match self.read() {
+                Ok(len) => len,
+                Err(e) => {
+                    error!(error = %e, "failed to read frame");
+                    return Some(Err(e.into()));
+                }
+            };
+            let sequence = self.sequence;
+            self.sequence = self.sequence.wrapping_add(1);
+
+            if self.skip == 0 {
+                break (len, sequence);
+            }
+            self.skip -= 1;
+            trace!(sequence, "discarding warmup frame");
+        };
+        trace!(sequence, len, "read frame");
+
+        let monotonic = now(libc::CLOCK_MONOTONIC);
+        let meta = BufferMetadata {
+            sequence: Some(sequence),
+            timestamp: Some(monotonic.saturating_sub(start)),
+            capture_time: Some(convert(
+                monotonic,
+                libc::CLOCK_MONOTONIC,
+                clock_id(self.clock),
+            )),
+            clock: Some(self.clock),
+            timestamp_source: Some(TimestampSource::EndOfFrame),
+            frame_type: match self.desc.as_ref().map(|desc| &desc.pixfmt) {
+                Some(PixelFormat::Jpeg) => Some(FrameType::Key),
+                _ => None,
+            },
+            field: None,
+        };
+
+        Some(Ok(Buffer::from(&self.buf[..len]).with_meta(meta)))
+    }
+
+    fn buffer_count(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn descriptor(&self) -> Option<Descriptor> {
+        self.desc.clone()
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        debug!("pausing stream");
+        self.paused = true;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        debug!("resuming stream");
+        // frames captured in the meantime are discarded like after a restart
+        self.paused = false;
+        self.skip = self.warmup;
+        Ok(())
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
@@ -1,25 +1,836 @@
+use std::{
+    alloc::{self, Layout},
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io, mem,
+    os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    path::Path,
+    ptr, slice,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use v4l::buffer::Type as BufType;
-use v4l::io::mmap::Stream as MmapStream;
-use v4l::io::traits::CaptureStream;
+use v4l::device::Handle as CaptureHandle;
+use v4l::memory::Memory;
+use v4l::v4l2;
+use v4l::v4l_sys::*;
+use v4l::video::Capture;
 
-use crate::buffer::Buffer;
-use crate::error::Result;
-use crate::platform::v4l2::device::Handle as DeviceHandle;
+use crate::buffer::{Buffer, Clock, FrameType, Metadata as BufferMetadata, TimestampSource};
+use crate::error::{Error, ErrorKind, Result};
+use crate::format::PixelFormat;
+use crate::geometry::Fraction;
+use crate::platform::v4l2::device::{ExtControl, ExtControls, Handle as DeviceHandle};
+use crate::stream::{
+    CacheHints, ConsumePolicy, CorruptFrames, Descriptor, FieldOrder, MemoryType, QueueState,
+};
 use crate::traits::Stream;
 
+/// `V4L2_BUF_CAP_SUPPORTS_USERPTR`: the queue accepts buffers allocated by the process
+const V4L2_BUF_CAP_SUPPORTS_USERPTR: u32 = 0x0000_0002;
+/// `V4L2_BUF_CAP_SUPPORTS_REQUESTS`: the queue accepts buffers bound to media requests
+const V4L2_BUF_CAP_SUPPORTS_REQUESTS: u32 = 0x0000_0008;
+/// `V4L2_BUF_FLAG_REQUEST_FD`: the `request_fd` field of the buffer is valid
+const V4L2_BUF_FLAG_REQUEST_FD: u32 = 0x0080_0000;
+/// `V4L2_CTRL_WHICH_REQUEST_VAL`: the controls are stored in a request
+const V4L2_CTRL_WHICH_REQUEST_VAL: u32 = 0x0f01_0000;
+
+/// `_IOR('|', 0x05, int)`
+const MEDIA_IOC_REQUEST_ALLOC: v4l2::vidioc::_IOC_TYPE =
+    ((2 << 30) | (mem::size_of::<i32>() << 16) | ((b'|' as usize) << 8) | 0x05)
+        as v4l2::vidioc::_IOC_TYPE;
+/// `_IO('|', 0x80)`
+const MEDIA_REQUEST_IOC_QUEUE: v4l2::vidioc::_IOC_TYPE =
+    (((b'|' as usize) << 8) | 0x80) as v4l2::vidioc::_IOC_TYPE;
+
 pub struct Handle<'a> {
-    stream: MmapStream<'a>,
+    /// Keeps the device open for as long as the buffers are mapped
+    _handle: Arc<CaptureHandle>,
+    fd: RawFd,
+    buf_type: BufType,
+    buffers: Vec<&'a [u8]>,
+    /// Memory backing the buffers
+    memory: Memory,
+    /// Size of the buffers allocated for user pointer I/O
+    userptr_size: usize,
+    /// Index of the buffer which was handed out by the last dequeue operation
+    active: Option<usize>,
+    streaming: bool,
+    paused: bool,
+    /// Flags passed to the driver when the buffers are allocated
+    memory_flags: u8,
+    /// Flags passed to the driver whenever a buffer is queued
+    queue_flags: u32,
+    events: bool,
+    /// Monotonic clock time at which streaming was started
+    start: Option<Duration>,
+    /// Instant and monotonic clock time of the last STREAMON
+    stream_on_at: Option<(Instant, Duration)>,
+    /// Time from the last STREAMON until the capture of the first frame after it
+    first_frame_latency: Option<Duration>,
+    clock: Clock,
+    corrupt_frames: CorruptFrames,
+    consume_policy: ConsumePolicy,
+    trim_jpeg: bool,
+    desc: Option<Descriptor>,
+    /// Maximum time to wait for a frame
+    timeout: Option<Duration>,
+    /// Number of frames to discard after streaming starts
+    warmup: usize,
+    /// Number of warmup frames which are still to be discarded
+    skip: usize,
+    /// Whether the queue accepts buffers bound to media requests
+    requests_supported: bool,
+    /// Media device used to allocate requests, buffers are only queued with requests if set
+    media: Option<File>,
+    /// Control values for the buffers queued next
+    controls: VecDeque<Vec<(u32, i64)>>,
+    /// Ownership of each buffer
+    slots: Vec<Slot>,
+}
+
+#[derive(Default)]
+struct Slot {
+    /// Whether the buffer is currently owned by the driver
+    queued: bool,
+    /// Request the buffer was queued with, kept open until the buffer is dequeued
+    _request: Option<OwnedFd>,
 }
 
 impl<'a> Handle<'a> {
-    pub fn new(dev: &DeviceHandle) -> Result<Self> {
-        let stream = MmapStream::new(dev.inner(), BufType::VideoCapture)?;
-        Ok(Handle { stream })
+    pub fn new(dev: &DeviceHandle, buf_type: BufType) -> Result<Self> {
+        Self::with_buffers(dev, buf_type, 4, CacheHints::NONE, MemoryType::Mmap)
+    }
+
+    pub fn with_buffers(
+        dev: &DeviceHandle,
+        buf_type: BufType,
+        buf_count: u32,
+        cache_hints: CacheHints,
+        memory: MemoryType,
+    ) -> Result<Self> {
+        let handle = dev.inner().handle();
+        let fd = handle.fd();
+        let memory = match memory {
+            MemoryType::Mmap => Memory::Mmap,
+            MemoryType::UserPtr => Memory::UserPtr,
+        };
+
+        // Requesting zero buffers only reports the capabilities of the queue.
+        let (_, caps) = request_buffers(fd, buf_type, memory, 0, 0)?;
+        let requests_supported = caps & V4L2_BUF_CAP_SUPPORTS_REQUESTS != 0;
+        let mut userptr_size = 0;
+        if memory == Memory::UserPtr {
+            // Kernels before 4.20 do not report any capabilities, allocating the buffers fails
+            // on its own then.
+            if caps != 0 && caps & V4L2_BUF_CAP_SUPPORTS_USERPTR == 0 {
+                return Err(Error::new(
+                    ErrorKind::NotSupported,
+                    "device does not support user pointer buffers",
+                ));
+            }
+            if !cache_hints.is_empty() {
+                return Err(Error::new(
+                    ErrorKind::NotSupported,
+                    "cache hints require memory mapped buffers",
+                ));
+            }
+
+            // The buffers must hold a complete frame of the current format.
+            userptr_size = dev.inner().format()?.size as usize;
+        }
+
+        let mut queue_flags = 0;
+        let mut memory_flags = 0;
+        if !cache_hints.is_empty() {
+            if caps & V4L2_BUF_CAP_SUPPORTS_MMAP_CACHE_HINTS == 0 {
+                return Err(Error::new(
+                    ErrorKind::NotSupported,
+                    "device does not support cache hints",
+                ));
+            }
+
+            // The hints are only honored for buffers which were allocated as non-coherent.
+            memory_flags = V4L2_MEMORY_FLAG_NON_COHERENT as u8;
+            if cache_hints.contains(CacheHints::NO_INVALIDATE) {
+                queue_flags |= V4L2_BUF_FLAG_NO_CACHE_INVALIDATE;
+            }
+            if cache_hints.contains(CacheHints::NO_CLEAN) {
+                queue_flags |= V4L2_BUF_FLAG_NO_CACHE_CLEAN;
+            }
+        }
+
+        // Only some devices (e.g. HDMI capture cards) emit source change events, so failing to
+        // subscribe is not an error.
+        let events = subscribe_source_change(fd).is_ok();
+
+        // The buffers are unmapped (or freed) and released when the handle is dropped, including
+        // the ones mapped so far if mapping fails halfway through.
+        let mut stream = Handle {
+            _handle: handle,
+            fd,
+            buf_type,
+            buffers: Vec::new(),
+            memory,
+            userptr_size,
+            active: None,
+            streaming: false,
+            paused: false,
+            memory_flags,
+            queue_flags,
+            events,
+            start: None,
+            stream_on_at: None,
+            first_frame_latency: None,
+            clock: Clock::default(),
+            corrupt_frames: CorruptFrames::default(),
+            consume_policy: ConsumePolicy::default(),
+            trim_jpeg: false,
+            desc: None,
+            timeout: None,
+            warmup: 0,
+            skip: 0,
+            requests_supported,
+            media: None,
+            controls: VecDeque::new(),
+            slots: Vec::new(),
+        };
+        // The buffers are queued and STREAMON is issued once the first frame is requested.
+        stream.allocate(buf_count)?;
+
+        Ok(stream)
+    }
+
+    /// Selects the clock for the buffer capture time
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Selects how frames which the driver flagged as corrupt are handled
+    pub fn with_corrupt_frames(mut self, corrupt_frames: CorruptFrames) -> Self {
+        self.corrupt_frames = corrupt_frames;
+        self
+    }
+
+    /// Selects whether stale frames are dropped
+    pub fn with_consume_policy(mut self, policy: ConsumePolicy) -> Self {
+        self.consume_policy = policy;
+        self
+    }
+
+    /// Selects whether JPEG frames are cut off after the end of image marker
+    pub fn with_jpeg_trimming(mut self, trim: bool) -> Self {
+        self.trim_jpeg = trim;
+        self
+    }
+
+    /// Limits the time to wait for a frame
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Discards a number of frames whenever streaming starts
+    pub fn with_warmup_frames(mut self, count: usize) -> Self {
+        self.warmup = count;
+        self
+    }
+
+    /// Sets the stream configuration negotiated with the driver
+    pub fn with_descriptor(mut self, desc: Descriptor) -> Self {
+        self.desc = Some(desc);
+        self
+    }
+
+    /// Queues all buffers through media requests, allowing per-frame control values
+    ///
+    /// `media` is the media controller node of the device (e.g. `/dev/media0`), which allocates
+    /// the requests. This must be called before the first frame is read. Only drivers
+    /// implementing the request API (mostly sensor drivers of embedded platforms) support this.
+    pub fn enable_requests<P: AsRef<Path>>(&mut self, media: P) -> Result<()> {
+        if !self.requests_supported {
+            return Err(Error::new(
+                ErrorKind::NotSupported,
+                "device does not support requests",
+            ));
+        }
+        if self.streaming {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "requests must be enabled before streaming starts",
+            ));
+        }
+
+        let media = OpenOptions::new().read(true).write(true).open(media)?;
+        debug!("enabling requests");
+        self.media = Some(media);
+        Ok(())
+    }
+
+    /// Applies control values to a single frame
+    ///
+    /// Takes (control ID, value) pairs. Each call binds the values to the next buffer handed to
+    /// the driver, which applies them right before capturing into that buffer. Since the buffers
+    /// are filled in order, consecutive calls affect consecutive frames, e.g. to alternate
+    /// between exposure times for HDR bracketing. Buffers queued while no values are pending
+    /// are captured with the current control values.
+    ///
+    /// All buffers are queued when streaming starts, so values queued before the first frame is
+    /// read apply to the first frames. Afterwards, a buffer is queued when the frame read before
+    /// it is released, i.e. values are applied a number of frames later which equals the buffer
+    /// count.
+    pub fn queue_controls(&mut self, controls: &[(u32, i64)]) -> Result<()> {
+        if self.media.is_none() {
+            return Err(Error::new(ErrorKind::Other, "requests are not enabled"));
+        }
+
+        self.controls.push_back(controls.to_vec());
+        Ok(())
+    }
+
+    /// Allocates a request holding the given control values
+    fn request(&self, media: &File, controls: &[(u32, i64)]) -> io::Result<OwnedFd> {
+        let mut fd: i32 = -1;
+        unsafe {
+            v4l2::ioctl(
+                media.as_raw_fd(),
+                MEDIA_IOC_REQUEST_ALLOC,
+                &mut fd as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+        let request = unsafe { OwnedFd::from_raw_fd(fd) };
+        if controls.is_empty() {
+            return Ok(request);
+        }
+
+        let mut ctrls: Vec<ExtControl> = controls
+            .iter()
+            .map(|&(id, value)| ExtControl {
+                id,
+                size: 0,
+                reserved: 0,
+                value,
+            })
+            .collect();
+        let mut ext = ExtControls {
+            which: V4L2_CTRL_WHICH_REQUEST_VAL,
+            count: ctrls.len() as u32,
+            error_idx: 0,
+            request_fd: fd,
+            reserved: 0,
+            controls: ctrls.as_mut_ptr(),
+        };
+        unsafe {
+            v4l2::ioctl(
+                self.fd,
+                v4l2::vidioc::VIDIOC_S_EXT_CTRLS,
+                &mut ext as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        Ok(request)
+    }
+
+    /// Requests a frame interval, returns the one granted by the driver
+    fn set_params(&self, interval: Duration) -> io::Result<Duration> {
+        let Fraction {
+            numerator,
+            denominator,
+        } = Fraction::from(interval);
+        unsafe {
+            let mut params: v4l2_streamparm = mem::zeroed();
+            params.type_ = self.buf_type as u32;
+            v4l2::ioctl(
+                self.fd,
+                v4l2::vidioc::VIDIOC_G_PARM,
+                &mut params as *mut _ as *mut std::os::raw::c_void,
+            )?;
+            params.parm.capture.timeperframe.numerator = numerator;
+            params.parm.capture.timeperframe.denominator = denominator;
+            v4l2::ioctl(
+                self.fd,
+                v4l2::vidioc::VIDIOC_S_PARM,
+                &mut params as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            // The driver writes back the closest interval it supports.
+            let granted = params.parm.capture.timeperframe;
+            if granted.numerator == 0 || granted.denominator == 0 {
+                return Ok(interval);
+            }
+            Ok(Fraction::new(granted.numerator, granted.denominator).into())
+        }
+    }
+
+    /// Waits until either a buffer is ready or a source change event is pending.
+    ///
+    /// Returns true if the source changed.
+    fn wait(&self) -> io::Result<bool> {
+        let mut pollfd = libc::pollfd {
+            fd: self.fd,
+            events: if self.events {
+                libc::POLLIN | libc::POLLPRI
+            } else {
+                libc::POLLIN
+            },
+            revents: 0,
+        };
+
+        let timeout = match self.timeout {
+            Some(timeout) => timeout.as_millis().min(i32::MAX as u128) as i32,
+            None => -1,
+        };
+        loop {
+            let ret = unsafe { libc::poll(&mut pollfd, 1, timeout) };
+            if ret == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "no frame arrived in time",
+                ));
+            }
+            if ret > 0 {
+                break;
+            }
+
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+
+        if pollfd.revents & libc::POLLPRI == 0 {
+            return Ok(false);
+        }
+
+        let mut changed = false;
+        unsafe {
+            let mut event: v4l2_event = mem::zeroed();
+            v4l2::ioctl(
+                self.fd,
+                v4l2::vidioc::VIDIOC_DQEVENT,
+                &mut event as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            if event.type_ == V4L2_EVENT_SOURCE_CHANGE
+                && event.u.src_change.changes & V4L2_EVENT_SRC_CH_RESOLUTION != 0
+            {
+                changed = true;
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Returns true if a buffer can be dequeued without blocking
+    fn ready(&self) -> bool {
+        let mut pollfd = libc::pollfd {
+            fd: self.fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        let ret = unsafe { libc::poll(&mut pollfd, 1, 0) };
+        ret > 0 && pollfd.revents & libc::POLLIN != 0
+    }
+
+    /// Allocates and maps the buffers
+    ///
+    /// The driver may grant a different number of buffers than requested.
+    fn allocate(&mut self, count: u32) -> io::Result<()> {
+        let (count, _) = request_buffers(
+            self.fd,
+            self.buf_type,
+            self.memory,
+            count,
+            self.memory_flags,
+        )?;
+        debug!(buffers = count, memory = ?self.memory, "allocated stream buffers");
+
+        self.buffers.reserve(count as usize);
+        self.slots.resize_with(count as usize, Slot::default);
+        for index in 0..count {
+            let buf = match self.memory {
+                Memory::UserPtr => self.alloc_userptr()?,
+                _ => self.map(index)?,
+            };
+            self.buffers.push(buf);
+        }
+
+        Ok(())
+    }
+
+    /// Unmaps and releases all buffers
+    ///
+    /// The stream must be stopped, see the `Drop` implementation for the required order.
+    fn release(&mut self) -> io::Result<()> {
+        self.slots.clear();
+        if self.memory == Memory::UserPtr {
+            // The driver may access user memory until the buffers are released.
+            request_buffers(self.fd, self.buf_type, self.memory, 0, 0)?;
+            for buf in self.buffers.drain(..) {
+                unsafe {
+                    alloc::dealloc(buf.as_ptr() as *mut u8, userptr_layout(buf.len()));
+                }
+            }
+            return Ok(());
+        }
+
+        for buf in self.buffers.drain(..) {
+            unsafe {
+                v4l2::munmap(buf.as_ptr() as *mut std::os::raw::c_void, buf.len())?;
+            }
+        }
+
+        request_buffers(self.fd, self.buf_type, self.memory, 0, 0)?;
+        Ok(())
+    }
+
+    /// Allocates a page aligned buffer for user pointer I/O
+    fn alloc_userptr(&self) -> io::Result<&'a [u8]> {
+        let layout = userptr_layout(self.userptr_size);
+        unsafe {
+            let ptr = alloc::alloc_zeroed(layout);
+            if ptr.is_null() {
+                return Err(io::Error::from(io::ErrorKind::OutOfMemory));
+            }
+            Ok(slice::from_raw_parts(ptr as *const u8, layout.size()))
+        }
+    }
+
+    /// Returns the minimum number of buffers required by the driver, if it reports one
+    fn min_buffers(&self) -> Option<usize> {
+        unsafe {
+            let mut ctrl: v4l2_control = mem::zeroed();
+            ctrl.id = V4L2_CID_MIN_BUFFERS_FOR_CAPTURE;
+            v4l2::ioctl(
+                self.fd,
+                v4l2::vidioc::VIDIOC_G_CTRL,
+                &mut ctrl as *mut _ as *mut std::os::raw::c_void,
+            )
+            .ok()?;
+            Some(ctrl.value as usize)
+        }
+    }
+
+    fn map(&self, index: u32) -> io::Result<&'a [u8]> {
+        unsafe {
+            let mut buf: v4l2_buffer = mem::zeroed();
+            buf.type_ = self.buf_type as u32;
+            buf.memory = Memory::Mmap as u32;
+            buf.index = index;
+            v4l2::ioctl(
+                self.fd,
+                v4l2::vidioc::VIDIOC_QUERYBUF,
+                &mut buf as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            let ptr = v4l2::mmap(
+                ptr::null_mut(),
+                buf.length as usize,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                self.fd,
+                buf.m.offset as libc::off_t,
+            )?;
+            Ok(slice::from_raw_parts(ptr as *const u8, buf.length as usize))
+        }
+    }
+
+    fn queue(&mut self, index: usize) -> io::Result<()> {
+        let controls = match self.media {
+            Some(_) => self.controls.pop_front(),
+            None => None,
+        };
+
+        let res = self.submit(index, controls.as_deref().unwrap_or_default());
+        if res.is_err() {
+            // The controls apply to the next buffer which makes it to the driver instead.
+            if let Some(controls) = controls {
+                self.controls.push_front(controls);
+            }
+        }
+        res
+    }
+
+    fn submit(&mut self, index: usize, controls: &[(u32, i64)]) -> io::Result<()> {
+        // Once a buffer was queued through a request, all of them have to be.
+        let request = match &self.media {
+            Some(media) => {
+                trace!(index, controls = controls.len(), "allocating request");
+                Some(self.request(media, controls)?)
+            }
+            None => None,
+        };
+
+        unsafe {
+            let mut buf: v4l2_buffer = mem::zeroed();
+            buf.type_ = self.buf_type as u32;
+            buf.memory = self.memory as u32;
+            buf.index = index as u32;
+            buf.flags = self.queue_flags;
+            if self.memory == Memory::UserPtr {
+                let data = self.buffers[index];
+                buf.m.userptr = data.as_ptr() as _;
+                buf.length = data.len() as u32;
+            }
+            if let Some(request) = &request {
+                buf.flags |= V4L2_BUF_FLAG_REQUEST_FD;
+                buf.__bindgen_anon_1.request_fd = request.as_raw_fd();
+            }
+            v4l2::ioctl(
+                self.fd,
+                v4l2::vidioc::VIDIOC_QBUF,
+                &mut buf as *mut _ as *mut std::os::raw::c_void,
+            )?;
+
+            // The buffer is only handed to the driver once the request is queued.
+            if let Some(request) = &request {
+                v4l2::ioctl(
+                    request.as_raw_fd(),
+                    MEDIA_REQUEST_IOC_QUEUE,
+                    ptr::null_mut(),
+                )?;
+            }
+        }
+
+        self.slots[index] = Slot {
+            queued: true,
+            _request: request,
+        };
+        Ok(())
+    }
+
+    fn dequeue(&mut self) -> io::Result<v4l2_buffer> {
+        unsafe {
+            let mut buf: v4l2_buffer = mem::zeroed();
+            buf.type_ = self.buf_type as u32;
+            buf.memory = self.memory as u32;
+            v4l2::ioctl(
+                self.fd,
+                v4l2::vidioc::VIDIOC_DQBUF,
+                &mut buf as *mut _ as *mut std::os::raw::c_void,
+            )?;
+            // the request is completed
+            if let Some(slot) = self.slots.get_mut(buf.index as usize) {
+                *slot = Slot::default();
+            }
+            Ok(buf)
+        }
+    }
+
+    /// Queues all buffers and starts streaming
+    fn stream_on(&mut self) -> io::Result<()> {
+        for index in 0..self.buffers.len() {
+            self.queue(index)?;
+        }
+
+        let mut typ = self.buf_type as u32;
+        unsafe {
+            v4l2::ioctl(
+                self.fd,
+                v4l2::vidioc::VIDIOC_STREAMON,
+                &mut typ as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        self.stream_on_at = Some((Instant::now(), now(libc::CLOCK_MONOTONIC)));
+        self.first_frame_latency = None;
+        self.streaming = true;
+        self.active = None;
+        self.skip = self.warmup;
+        Ok(())
+    }
+
+    /// Stops streaming, which implicitly dequeues all buffers
+    fn stream_off(&mut self) -> io::Result<()> {
+        let mut typ = self.buf_type as u32;
+        unsafe {
+            v4l2::ioctl(
+                self.fd,
+                v4l2::vidioc::VIDIOC_STREAMOFF,
+                &mut typ as *mut _ as *mut std::os::raw::c_void,
+            )?;
+        }
+
+        self.slots
+            .iter_mut()
+            .for_each(|slot| *slot = Slot::default());
+        self.streaming = false;
+        self.active = None;
+        Ok(())
+    }
+}
+
+/// Returns the current time of a clock
+pub(crate) fn now(clock: libc::clockid_t) -> Duration {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(clock, &mut ts);
+    }
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+/// Converts a timestamp from one clock to another
+pub(crate) fn convert(ts: Duration, from: libc::clockid_t, to: libc::clockid_t) -> Duration {
+    if from == to {
+        return ts;
     }
 
-    pub fn with_buffers(dev: &DeviceHandle, buf_count: u32) -> Result<Self> {
-        let stream = MmapStream::with_buffers(dev.inner(), BufType::VideoCapture, buf_count)?;
-        Ok(Handle { stream })
+    let offset = now(to).as_nanos() as i128 - now(from).as_nanos() as i128;
+    let nanos = ts.as_nanos() as i128 + offset;
+    Duration::from_nanos(nanos.max(0) as u64)
+}
+
+pub(crate) fn clock_id(clock: Clock) -> libc::clockid_t {
+    match clock {
+        Clock::Monotonic => libc::CLOCK_MONOTONIC,
+        Clock::MonotonicRaw => libc::CLOCK_MONOTONIC_RAW,
+        Clock::Boottime => libc::CLOCK_BOOTTIME,
+        Clock::Realtime => libc::CLOCK_REALTIME,
+    }
+}
+
+/// Converts the buffer timestamp to the monotonic clock
+///
+/// Depending on the driver, the timestamp is either taken from the monotonic clock or from the
+/// realtime clock (very old drivers which report an unknown timestamp type). Memory-to-memory
+/// devices copy the timestamp from the output buffers, which is in no particular timebase, so we
+/// use the dequeue time instead.
+fn monotonic_timestamp(buf: &v4l2_buffer) -> Duration {
+    let ts = Duration::new(
+        buf.timestamp.tv_sec as u64,
+        buf.timestamp.tv_usec as u32 * 1000,
+    );
+
+    match buf.flags & V4L2_BUF_FLAG_TIMESTAMP_MASK {
+        V4L2_BUF_FLAG_TIMESTAMP_MONOTONIC => ts,
+        V4L2_BUF_FLAG_TIMESTAMP_UNKNOWN => convert(ts, libc::CLOCK_REALTIME, libc::CLOCK_MONOTONIC),
+        _ => now(libc::CLOCK_MONOTONIC),
+    }
+}
+
+/// Returns the coding type of a compressed frame
+///
+/// Drivers only flag the frame type of formats with inter frame compression, MJPEG frames are all
+/// keyframes.
+fn frame_type(buf: &v4l2_buffer, desc: Option<&Descriptor>) -> Option<FrameType> {
+    if buf.flags & V4L2_BUF_FLAG_KEYFRAME != 0 {
+        Some(FrameType::Key)
+    } else if buf.flags & V4L2_BUF_FLAG_PFRAME != 0 {
+        Some(FrameType::Predicted)
+    } else if buf.flags & V4L2_BUF_FLAG_BFRAME != 0 {
+        Some(FrameType::Bidirectional)
+    } else {
+        match desc.map(|desc| &desc.pixfmt) {
+            Some(PixelFormat::Jpeg) => Some(FrameType::Key),
+            _ => None,
+        }
+    }
+}
+
+/// Maps a `enum v4l2_field` value
+fn field_order(field: u32) -> Option<FieldOrder> {
+    match field {
+        1 => Some(FieldOrder::Progressive),
+        2 => Some(FieldOrder::Top),
+        3 => Some(FieldOrder::Bottom),
+        4 => Some(FieldOrder::Interlaced),
+        5 => Some(FieldOrder::SequentialTopBottom),
+        6 => Some(FieldOrder::SequentialBottomTop),
+        7 => Some(FieldOrder::Alternate),
+        8 => Some(FieldOrder::InterlacedTopFirst),
+        9 => Some(FieldOrder::InterlacedBottomFirst),
+        // V4L2_FIELD_ANY is never reported for dequeued buffers
+        _ => None,
+    }
+}
+
+/// Returns the length of a JPEG image up to and including the end of image marker
+///
+/// The search starts at the end, since embedded thumbnails have end of image markers as well.
+fn jpeg_end(data: &[u8]) -> Option<usize> {
+    data.windows(2)
+        .rposition(|marker| marker == [0xFF, 0xD9])
+        .map(|pos| pos + 2)
+}
+
+/// Requests buffers from the driver
+///
+/// Returns the number of buffers which were allocated and the capabilities of the queue.
+fn request_buffers(
+    fd: RawFd,
+    buf_type: BufType,
+    memory: Memory,
+    count: u32,
+    flags: u8,
+) -> io::Result<(u32, u32)> {
+    unsafe {
+        let mut req: v4l2_requestbuffers = mem::zeroed();
+        req.count = count;
+        req.type_ = buf_type as u32;
+        req.memory = memory as u32;
+        req.flags = flags;
+        v4l2::ioctl(
+            fd,
+            v4l2::vidioc::VIDIOC_REQBUFS,
+            &mut req as *mut _ as *mut std::os::raw::c_void,
+        )?;
+        Ok((req.count, req.capabilities))
+    }
+}
+
+/// Returns the layout of a user pointer buffer, which is rounded up to whole pages
+///
+/// Drivers which capture into user memory through DMA map it page by page.
+fn userptr_layout(size: usize) -> Layout {
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as usize;
+    let size = size.max(1).div_ceil(page) * page;
+    Layout::from_size_align(size, page).expect("page size is a power of two")
+}
+
+fn subscribe_source_change(fd: RawFd) -> io::Result<()> {
+    unsafe {
+        let mut sub: v4l2_event_subscription = mem::zeroed();
+        sub.type_ = V4L2_EVENT_SOURCE_CHANGE;
+        v4l2::ioctl(
+            fd,
+            v4l2::vidioc::VIDIOC_SUBSCRIBE_EVENT,
+            &mut sub as *mut _ as *mut std::os::raw::c_void,
+        )
+    }
+}
+
+/// Stops the stream and frees the buffers
+///
+/// The steps have to happen in this order:
+///
+/// 1. `STREAMOFF` returns all queued buffers to the application. Until then, the driver may
+///    still write into them, and releasing the buffers fails with `EBUSY`.
+/// 2. Memory mapped buffers are unmapped before they are released, since older kernels refuse
+///    to release buffers which are still mapped. User pointer buffers are the other way around:
+///    the driver may access the memory until the buffers are released, so it is only freed
+///    afterwards.
+/// 3. `REQBUFS` with a count of zero releases the buffers.
+///
+/// The device is kept open by `_handle`, which is only dropped after this, and the frames returned
+/// by [`Stream::next`] borrow the stream, so none of them can outlive the buffers.
+impl<'a> Drop for Handle<'a> {
+    fn drop(&mut self) {
+        debug!("stopping stream");
+        if self.streaming {
+            if let Err(_e) = self.stream_off() {
+                error!(error = %_e, "failed to stop stream");
+            }
+        }
+
+        if let Err(_e) = self.release() {
+            error!(error = %_e, "failed to release buffers");
+        }
     }
 }
 
@@ -27,14 +838,279 @@ impl<'a, 'b> Stream<'b> for Handle<'a> {
     type Item = Result<Buffer<'b>>;
 
     fn next(&'b mut self) -> Option<Self::Item> {
-        match CaptureStream::next(&mut self.stream) {
-            Err(e) => Some(Err(e.into())),
-            Ok(None) => None,
-            Ok(Some((buffer, meta))) => {
-                let view = &buffer[0..meta.bytesused as usize];
+        if self.paused {
+            return None;
+        }
 
-                Some(Ok(Buffer::from(view)))
+        let res = if self.streaming {
+            // Hand the previous buffer back to the driver before waiting for the next one.
+            match self.active.take() {
+                Some(index) => self.queue(index),
+                None => Ok(()),
             }
+        } else {
+            self.stream_on()
+        };
+        if let Err(e) = res {
+            error!(error = %e, "failed to queue buffer");
+            return Some(Err(e.into()));
+        }
+        if self.start.is_none() {
+            self.start = Some(now(libc::CLOCK_MONOTONIC));
         }
+
+        let mut buf = loop {
+            // Waiting first also makes this work for devices which were opened in non-blocking
+            // mode.
+            match self.wait() {
+                Ok(false) => {}
+                Ok(true) => {
+                    debug!("source changed");
+                    return Some(Err(Error::new(
+                        ErrorKind::SourceChanged,
+                        "source resolution changed",
+                    )));
+                }
+                Err(e) => return Some(Err(e.into())),
+            }
+
+            let buf = match self.dequeue() {
+                Ok(buf) => buf,
+                Err(e) => {
+                    error!(error = %e, "failed to dequeue buffer");
+                    return Some(Err(e.into()));
+                }
+            };
+            if buf.flags & V4L2_BUF_FLAG_ERROR == 0 {
+                if self.skip == 0 {
+                    break buf;
+                }
+
+                self.skip -= 1;
+                trace!(
+                    index = buf.index,
+                    sequence = buf.sequence,
+                    "discarding warmup frame"
+                );
+                if let Err(e) = self.queue(buf.index as usize) {
+                    error!(error = %e, "failed to queue buffer");
+                    return Some(Err(e.into()));
+                }
+                continue;
+            }
+
+            // The buffer data is incomplete (e.g. a truncated frame), so hand the buffer straight
+            // back to the driver.
+            debug!(
+                index = buf.index,
+                sequence = buf.sequence,
+                bytesused = buf.bytesused,
+                "dequeued corrupt buffer"
+            );
+            if let Err(e) = self.queue(buf.index as usize) {
+                error!(error = %e, "failed to queue buffer");
+                return Some(Err(e.into()));
+            }
+            if self.corrupt_frames == CorruptFrames::Surface {
+                return Some(Err(Error::new(
+                    ErrorKind::CorruptFrame,
+                    "driver flagged the frame as corrupt",
+                )));
+            }
+        };
+        if self.consume_policy == ConsumePolicy::KeepLatest {
+            // Drivers fill the buffers in order, so all but the last filled buffer are stale.
+            while self.ready() {
+                let newer = match self.dequeue() {
+                    Ok(newer) => newer,
+                    Err(e) => {
+                        error!(error = %e, "failed to dequeue buffer");
+                        return Some(Err(e.into()));
+                    }
+                };
+                // Keep the older frame if the newer one is corrupt.
+                let stale = if newer.flags & V4L2_BUF_FLAG_ERROR == 0 {
+                    mem::replace(&mut buf, newer)
+                } else {
+                    newer
+                };
+                trace!(
+                    index = stale.index,
+                    sequence = stale.sequence,
+                    "dropping stale buffer"
+                );
+                if let Err(e) = self.queue(stale.index as usize) {
+                    error!(error = %e, "failed to queue buffer");
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+        trace!(
+            index = buf.index,
+            sequence = buf.sequence,
+            sec = buf.timestamp.tv_sec,
+            usec = buf.timestamp.tv_usec,
+            bytesused = buf.bytesused,
+            "dequeued buffer"
+        );
+        self.active = Some(buf.index as usize);
+
+        let start = self.start.unwrap_or_default();
+        let monotonic = monotonic_timestamp(&buf);
+        if self.first_frame_latency.is_none() {
+            if let Some((_, at)) = self.stream_on_at {
+                self.first_frame_latency = Some(monotonic.saturating_sub(at));
+            }
+        }
+        let timestamp_source = match buf.flags & V4L2_BUF_FLAG_TSTAMP_SRC_MASK {
+            V4L2_BUF_FLAG_TSTAMP_SRC_SOE => TimestampSource::StartOfExposure,
+            _ => TimestampSource::EndOfFrame,
+        };
+        let meta = BufferMetadata {
+            sequence: Some(buf.sequence),
+            timestamp: Some(monotonic.saturating_sub(start)),
+            capture_time: Some(convert(
+                monotonic,
+                libc::CLOCK_MONOTONIC,
+                clock_id(self.clock),
+            )),
+            clock: Some(self.clock),
+            timestamp_source: Some(timestamp_source),
+            frame_type: frame_type(&buf, self.desc.as_ref()),
+            field: field_order(buf.field),
+        };
+
+        let data = match self.buffers.get(buf.index as usize) {
+            Some(data) => *data,
+            None => {
+                return Some(Err(Error::new(
+                    ErrorKind::Other,
+                    format!("driver returned unknown buffer {}", buf.index),
+                )))
+            }
+        };
+        // Buggy drivers may report more bytes than the buffer holds.
+        let mut view = match data.get(..buf.bytesused as usize) {
+            Some(view) => view,
+            None => {
+                error!(
+                    index = buf.index,
+                    bytesused = buf.bytesused,
+                    length = data.len(),
+                    "driver reported invalid buffer length"
+                );
+                return Some(Err(Error::new(
+                    ErrorKind::InvalidBufferLength,
+                    format!(
+                        "driver reported {} bytes for a buffer of {} bytes",
+                        buf.bytesused,
+                        data.len()
+                    ),
+                )));
+            }
+        };
+        if self.trim_jpeg && matches!(&self.desc, Some(desc) if desc.pixfmt == PixelFormat::Jpeg) {
+            if let Some(end) = jpeg_end(view) {
+                view = &view[..end];
+            }
+        }
+        Some(Ok(Buffer::from(view).with_meta(meta)))
+    }
+
+    fn buffer_count(&self) -> Option<usize> {
+        Some(self.buffers.len())
+    }
+
+    fn last_buffer_index(&self) -> Option<u32> {
+        self.active.map(|index| index as u32)
+    }
+
+    fn start_instant(&self) -> Option<Instant> {
+        self.stream_on_at.map(|(instant, _)| instant)
+    }
+
+    fn first_frame_latency(&self) -> Option<Duration> {
+        self.first_frame_latency
+    }
+
+    fn queue_state(&self) -> Option<QueueState> {
+        let queued = self.slots.iter().filter(|slot| slot.queued).count();
+        Some(QueueState {
+            queued,
+            dequeued: self.buffers.len() - queued,
+            total: self.buffers.len(),
+        })
+    }
+
+    fn descriptor(&self) -> Option<Descriptor> {
+        self.desc.clone()
+    }
+
+    /// Changes the frame interval with `VIDIOC_S_PARM`
+    ///
+    /// Drivers which cannot change the interval while streaming reject the request with `EBUSY`
+    /// (or `EINVAL` for some older ones). In that case, the stream is stopped and the interval
+    /// is set again. The buffers are kept and the next dequeue operation restarts the stream.
+    ///
+    /// The interval granted by the driver is reported by [`Stream::descriptor`] afterwards.
+    fn set_interval(&mut self, interval: Duration) -> Result<()> {
+        debug!(?interval, "changing frame interval");
+        let granted = match self.set_params(interval) {
+            Ok(granted) => granted,
+            Err(e)
+                if matches!(e.raw_os_error(), Some(libc::EBUSY) | Some(libc::EINVAL))
+                    && self.streaming =>
+            {
+                debug!(error = %e, "restarting stream to change frame interval");
+                self.stream_off()?;
+                self.set_params(interval)?
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        trace!(?granted, "frame interval granted");
+        if let Some(desc) = &mut self.desc {
+            desc.interval = granted;
+        }
+        Ok(())
+    }
+
+    fn set_buffer_count(&mut self, count: usize) -> Result<()> {
+        let min = self.min_buffers().unwrap_or(1).max(1);
+        if count < min {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!("driver requires at least {} buffers", min),
+            ));
+        }
+
+        debug!(count, "changing buffer count");
+        if self.streaming {
+            // Like changing the frame interval, the next dequeue operation restarts the stream.
+            self.stream_off()?;
+        }
+        self.release()?;
+        self.allocate(count as u32)?;
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        if self.streaming {
+            debug!("pausing stream");
+            self.stream_off()?;
+        }
+        self.paused = true;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        // The next dequeue operation queues the buffers and restarts the stream.
+        debug!("resuming stream");
+        self.paused = false;
+        Ok(())
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused
     }
 }
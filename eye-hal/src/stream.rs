@@ -1,8 +1,17 @@
-use std::time;
+use alloc::{
+    string::ToString,
+    vec::{self, Vec},
+};
+use core::{cmp::Reverse, iter::FromIterator, ops::Deref, slice, time};
 
+use bitflags::bitflags;
+
+use crate::buffer::Clock;
 use crate::format::PixelFormat;
+use crate::geometry::{Fraction, Size};
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Image stream description
 pub struct Descriptor {
     /// Width in pixels
@@ -12,12 +21,557 @@ pub struct Descriptor {
     /// PixelFormat
     pub pixfmt: PixelFormat,
     /// Frame timing as duration
+    ///
+    /// Serialized as the number of frames per second.
+    #[cfg_attr(feature = "serde", serde(with = "fps"))]
     pub interval: time::Duration,
+    /// Whether the format is converted in software instead of being produced by the hardware
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub emulated: bool,
+}
+
+impl Descriptor {
+    /// Returns the number of frames per second
+    pub fn fps(&self) -> f64 {
+        1.0 / self.interval.as_secs_f64()
+    }
+
+    /// Returns the descriptor with the frame interval set to match a frame rate
+    ///
+    /// NTSC rates such as 29.97 or 59.94 fps are taken as the exact fractions they stand for
+    /// (30000/1001 and 60000/1001), so they survive the round-trip through
+    /// [`Self::interval_fraction`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fps` is not a positive, finite number.
+    pub fn with_fps(mut self, fps: f64) -> Self {
+        self.interval = fps_interval(fps);
+        self
+    }
+
+    /// Returns the frame interval as the simplest fraction of seconds
+    pub fn interval_fraction(&self) -> Fraction {
+        Fraction::from(self.interval)
+    }
+
+    /// Returns the frame size
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    /// Returns the size of a tightly packed frame in bytes
+    ///
+    /// Returns `None` for compressed formats, whose frame size depends on the image content, and
+    /// for formats of unknown depth.
+    pub fn frame_bytes(&self) -> Option<usize> {
+        if self.pixfmt.is_compressed() {
+            return None;
+        }
+        if let Some(planes) = self.pixfmt.planes(self.width, self.height) {
+            return Some(planes.len);
+        }
+
+        let bits = self.pixfmt.bits()? as usize;
+        Some(self.width as usize * self.height as usize * bits / 8)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// Collection of image stream descriptions
+///
+/// Offers combinators to narrow down the streams of a device, e.g.:
+///
+/// ```
+/// # use eye_hal::format::PixelFormat;
+/// # use eye_hal::stream::Descriptors;
+/// # let streams = Vec::new();
+/// let best = Descriptors::from(streams)
+///     .with_format(PixelFormat::Jpeg)
+///     .min_fps(30.0)
+///     .max_by_resolution();
+/// ```
+pub struct Descriptors(Vec<Descriptor>);
+
+impl Descriptors {
+    /// Retains the streams with the given pixel format
+    pub fn with_format(self, pixfmt: PixelFormat) -> Self {
+        self.filter(|desc| desc.pixfmt == pixfmt)
+    }
+
+    /// Retains the streams with at least the given resolution
+    pub fn at_least(self, width: u32, height: u32) -> Self {
+        self.filter(|desc| desc.width >= width && desc.height >= height)
+    }
+
+    /// Retains the streams with at most the given resolution
+    pub fn at_most(self, width: u32, height: u32) -> Self {
+        self.filter(|desc| desc.width <= width && desc.height <= height)
+    }
+
+    /// Retains the streams which deliver at least the given number of frames per second
+    pub fn min_fps(self, fps: f64) -> Self {
+        // Allow for rounding errors, intervals like 1/30s cannot be represented exactly.
+        self.filter(|desc| desc.fps() + 1e-3 >= fps)
+    }
+
+    /// Retains the streams matching a predicate
+    pub fn filter<F: FnMut(&Descriptor) -> bool>(self, mut f: F) -> Self {
+        Descriptors(self.0.into_iter().filter(|desc| f(desc)).collect())
+    }
+
+    /// Sorts the streams and removes duplicates
+    ///
+    /// Streams are ordered by resolution (most pixels first, then widest first), then by the name
+    /// of the pixel format and finally by frame rate (fastest first). Streams which only differ
+    /// in whether they are emulated are merged, keeping the native stream.
+    pub fn normalized(self) -> Self {
+        let mut streams = self.0;
+        streams.sort_by_cached_key(|desc| {
+            (
+                Reverse(desc.width as u64 * desc.height as u64),
+                Reverse(desc.width),
+                desc.pixfmt.to_string(),
+                desc.interval,
+                desc.emulated,
+            )
+        });
+        // Intervals are usually computed from fractions, so allow for rounding errors.
+        streams.dedup_by(|b, a| {
+            a.width == b.width
+                && a.height == b.height
+                && a.pixfmt == b.pixfmt
+                && a.interval.max(b.interval) - a.interval.min(b.interval)
+                    < time::Duration::from_micros(1)
+        });
+        Descriptors(streams)
+    }
+
+    /// Returns the stream with the highest resolution
+    ///
+    /// If multiple streams have the same resolution, the one with the highest frame rate wins.
+    pub fn max_by_resolution(self) -> Option<Descriptor> {
+        self.0.into_iter().max_by(|a, b| {
+            (a.width * a.height)
+                .cmp(&(b.width * b.height))
+                .then(b.interval.cmp(&a.interval))
+        })
+    }
+
+    /// Returns the stream with the highest frame rate
+    ///
+    /// If multiple streams have the same frame rate, the one with the highest resolution wins.
+    pub fn max_by_fps(self) -> Option<Descriptor> {
+        self.0.into_iter().max_by(|a, b| {
+            b.interval
+                .cmp(&a.interval)
+                .then((a.width * a.height).cmp(&(b.width * b.height)))
+        })
+    }
+}
+
+impl Deref for Descriptors {
+    type Target = [Descriptor];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Vec<Descriptor>> for Descriptors {
+    fn from(streams: Vec<Descriptor>) -> Self {
+        Descriptors(streams)
+    }
+}
+
+impl From<Descriptors> for Vec<Descriptor> {
+    fn from(streams: Descriptors) -> Self {
+        streams.0
+    }
+}
+
+impl FromIterator<Descriptor> for Descriptors {
+    fn from_iter<I: IntoIterator<Item = Descriptor>>(iter: I) -> Self {
+        Descriptors(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for Descriptors {
+    type Item = Descriptor;
+    type IntoIter = vec::IntoIter<Descriptor>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Descriptors {
+    type Item = &'a Descriptor;
+    type IntoIter = slice::Iter<'a, Descriptor>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// Stream settings, needed on stream open operation.
+///
+/// With the `serde` feature, the settings can be serialized, e.g. to log them. Since they borrow
+/// the descriptor, persist the [`Descriptor`] itself and rebuild the settings from it instead.
 pub struct DeviceStreamSettings<'a> {
     pub desc: &'a Descriptor,
+    /// Number of buffers to request, the driver may allocate a different amount
     pub buffers_count: Option<usize>,
+    /// Clock used for the buffer capture time
+    pub clock: Clock,
+    /// Cache maintenance the kernel may skip for the stream buffers
+    pub cache_hints: CacheHints,
+    /// Handling of frames which the driver flagged as corrupt
+    pub corrupt_frames: CorruptFrames,
+    /// Handling of frames which queued up while the consumer was busy
+    pub consume_policy: ConsumePolicy,
+    /// Whether JPEG frames are cut off after the end of image marker
+    pub trim_jpeg: bool,
+    /// Field order to request for interlaced sources, the driver chooses one if not set
+    pub field_order: Option<FieldOrder>,
+    /// Latency budget used to derive the number of buffers if `buffers_count` is not set
+    #[cfg_attr(feature = "serde", serde(serialize_with = "millis"))]
+    pub latency: Option<time::Duration>,
+    /// Maximum time to wait for a frame, waits forever if not set
+    #[cfg_attr(feature = "serde", serde(serialize_with = "millis"))]
+    pub timeout: Option<time::Duration>,
+    /// Number of frames to discard whenever the capture starts
+    pub warmup_frames: usize,
+    /// Whether sizes the device does not offer are produced by scaling down a larger size
+    pub scale_to_requested: bool,
+    /// Memory backing the buffers, the device default is used if not set
+    pub memory: Option<MemoryType>,
+}
+
+impl<'a> DeviceStreamSettings<'a> {
+    /// Returns the default settings for a stream
+    pub fn new(desc: &'a Descriptor) -> Self {
+        DeviceStreamSettings {
+            desc,
+            buffers_count: None,
+            clock: Clock::default(),
+            cache_hints: CacheHints::NONE,
+            corrupt_frames: CorruptFrames::default(),
+            consume_policy: ConsumePolicy::default(),
+            trim_jpeg: false,
+            field_order: None,
+            latency: None,
+            timeout: None,
+            warmup_frames: 0,
+            scale_to_requested: false,
+            memory: None,
+        }
+    }
+
+    /// Requests a number of buffers
+    ///
+    /// Use [`Stream::buffer_count`](crate::traits::Stream::buffer_count) to retrieve the number
+    /// of buffers which were actually allocated.
+    pub fn with_buffers_count(mut self, count: usize) -> Self {
+        self.buffers_count = Some(count);
+        self
+    }
+
+    /// Derives the number of buffers from a latency budget
+    ///
+    /// See [`buffers_for_latency`] for how the count is computed. An explicit buffer count takes
+    /// precedence.
+    pub fn with_latency_budget(mut self, latency: time::Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Selects the clock for the buffer capture time
+    ///
+    /// Drivers usually timestamp buffers using the monotonic clock, capture times of other clocks
+    /// are derived by measuring the offset between the clocks when a buffer is dequeued.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sets the cache hints for the stream buffers
+    ///
+    /// Starting the stream fails if the device does not support cache hints.
+    pub fn with_cache_hints(mut self, hints: CacheHints) -> Self {
+        self.cache_hints = hints;
+        self
+    }
+
+    /// Selects how frames which the driver flagged as corrupt are handled
+    pub fn with_corrupt_frames(mut self, corrupt_frames: CorruptFrames) -> Self {
+        self.corrupt_frames = corrupt_frames;
+        self
+    }
+
+    /// Selects whether a slow consumer receives every frame or only the latest one
+    pub fn with_consume_policy(mut self, policy: ConsumePolicy) -> Self {
+        self.consume_policy = policy;
+        self
+    }
+
+    /// Cuts off JPEG frames after the end of image marker
+    ///
+    /// Some drivers report padded frame sizes, so the frames carry trailing garbage which strict
+    /// decoders reject. Finding the marker requires scanning the frame, so this is off by
+    /// default.
+    pub fn with_jpeg_trimming(mut self, trim: bool) -> Self {
+        self.trim_jpeg = trim;
+        self
+    }
+
+    /// Requests a field order, e.g. for capture cards with interlaced sources
+    ///
+    /// The field order the driver chose is reported in the metadata of each buffer.
+    pub fn with_field_order(mut self, order: FieldOrder) -> Self {
+        self.field_order = Some(order);
+        self
+    }
+
+    /// Limits the time to wait for a frame
+    ///
+    /// If no frame arrives in time, the stream returns an error of kind
+    /// [`ErrorKind::Timeout`](crate::error::ErrorKind::Timeout) and keeps running, so the next
+    /// call waits again.
+    pub fn with_timeout(mut self, timeout: time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Discards a number of frames whenever the capture starts
+    ///
+    /// The first frames are often too dark or otherwise off while the auto exposure and white
+    /// balance converge. The frames are discarded when the stream starts as well as when it
+    /// restarts (e.g. after it was resumed), so [`Stream::next`](crate::traits::Stream::next)
+    /// only returns settled frames. Defaults to 0.
+    pub fn with_warmup_frames(mut self, count: usize) -> Self {
+        self.warmup_frames = count;
+        self
+    }
+
+    /// Scales frames to the requested size if the device does not offer it
+    ///
+    /// The stream is started with the smallest size which is larger than the requested one in
+    /// both dimensions and its frames are scaled down in software. The aspect ratio is not
+    /// preserved. Only devices which convert frames in software (e.g. `eye::colorconvert::Device`)
+    /// honor this, the platform devices ignore it.
+    pub fn with_scale_to_requested(mut self, scale: bool) -> Self {
+        self.scale_to_requested = scale;
+        self
+    }
+
+    /// Selects the memory backing the stream buffers
+    ///
+    /// This overrides the default of the device, which in turn may be inherited from the context
+    /// it was opened by (see `eye_hal::platform::Context::with_memory`). Starting the stream
+    /// fails if the device does not support the memory type.
+    pub fn with_memory(mut self, memory: MemoryType) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+}
+
+/// Returns the number of buffers which keeps the latency within a budget
+///
+/// Every queued buffer may hold a frame which waits for the consumer, so a frame can be up to
+/// one frame interval per buffer old when it is dequeued. More buffers make the stream more
+/// tolerant to a consumer which is late every now and then, fewer buffers keep the latency low.
+///
+/// The result is never below the minimum the driver requires, nor below two buffers, which are
+/// needed to capture a frame while the consumer holds the previous one.
+pub fn buffers_for_latency(fps: f64, latency: time::Duration, driver_min: usize) -> usize {
+    let frames = latency.as_secs_f64() * fps;
+    // the cast truncates, negative and NaN products become 0
+    let frames = if frames.is_finite() {
+        frames as usize
+    } else {
+        0
+    };
+    frames.max(driver_min).max(2)
+}
+
+#[cfg(feature = "serde")]
+/// (De)serializes frame intervals as the number of frames per second
+mod fps {
+    use core::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::geometry::Fraction;
+
+    pub fn serialize<S: Serializer>(interval: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        // Going through the fraction yields round numbers, e.g. 30 instead of 30.0000003.
+        let fraction = Fraction::from(*interval);
+        serializer.serialize_f64(fraction.denominator as f64 / fraction.numerator.max(1) as f64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let fps = f64::deserialize(deserializer)?;
+        if !(fps.is_finite() && fps > 0.0) {
+            return Err(serde::de::Error::custom(
+                "frame rate must be a positive number",
+            ));
+        }
+        Ok(super::fps_interval(fps))
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Serializes optional durations as milliseconds
+fn millis<S: serde::Serializer>(
+    duration: &Option<time::Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => serializer.serialize_some(&(duration.as_secs_f64() * 1000.0)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Returns the frame interval for a number of frames per second
+fn fps_interval(fps: f64) -> time::Duration {
+    // NTSC rates are specified as multiples of 1000/1001. Rates are positive, so casting rounds
+    // them without the float functions which are missing in `core` (including f64::abs).
+    let ntsc = fps * 1.001;
+    let rounded = (ntsc + 0.5) as u64;
+    let fract = fps - fps as u64 as f64;
+    let denominator = rounded
+        .checked_mul(1000)
+        .filter(|&denominator| denominator <= u32::MAX as u64);
+    let d = ntsc - rounded as f64;
+    let error = if d < 0.0 { -d } else { d };
+    if let Some(denominator) = denominator {
+        if rounded >= 1 && error < 1e-3 && fract > 1e-6 {
+            return Fraction::new(1001, denominator as u32).into();
+        }
+    }
+
+    time::Duration::from_secs_f64(1.0 / fps)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Handling of corrupt frames
+///
+/// Drivers flag frames which were not captured completely (e.g. because of transmission errors),
+/// their buffers usually only contain part of the image data.
+pub enum CorruptFrames {
+    /// Drop corrupt frames and wait for the next one
+    #[default]
+    Skip,
+    /// Return an error of kind [`ErrorKind::CorruptFrame`](crate::error::ErrorKind::CorruptFrame)
+    Surface,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Arrangement of the fields of a frame
+///
+/// Interlaced sources transmit the even (top) and odd (bottom) lines of an image as separate
+/// fields, each of which was captured at a different point in time.
+pub enum FieldOrder {
+    /// Progressive image, there are no fields
+    Progressive,
+    /// Top field only
+    Top,
+    /// Bottom field only
+    Bottom,
+    /// Both fields interleaved line by line, the temporal order is unknown
+    Interlaced,
+    /// Both fields interleaved line by line, the top field was captured first
+    InterlacedTopFirst,
+    /// Both fields interleaved line by line, the bottom field was captured first
+    InterlacedBottomFirst,
+    /// Top field followed by the bottom field in the same buffer
+    SequentialTopBottom,
+    /// Bottom field followed by the top field in the same buffer
+    SequentialBottomTop,
+    /// Fields in separate buffers, alternating between top and bottom
+    ///
+    /// The metadata of each buffer tells which field it contains.
+    Alternate,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Handling of frames which queue up while the consumer is busy
+///
+/// Frames are captured into a limited number of buffers. Once all of them are filled, the
+/// device has to wait for the consumer to return a buffer, which drops (or delays) newer frames.
+pub enum ConsumePolicy {
+    /// Deliver every captured frame in order, at the cost of latency when the consumer falls
+    /// behind
+    #[default]
+    Fifo,
+    /// Deliver only the most recent frame, stale frames are dropped
+    ///
+    /// This keeps the latency low, e.g. for live previews.
+    KeepLatest,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Memory backing the stream buffers
+pub enum MemoryType {
+    /// Buffers allocated by the driver and mapped into the process
+    #[default]
+    Mmap,
+    /// Buffers allocated by the process, which the driver captures into directly
+    ///
+    /// Some drivers (e.g. those of PCI capture cards) perform better with user memory, others do
+    /// not support it at all. Cache hints are only available for memory mapped buffers.
+    UserPtr,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for CacheHints {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.bits())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CacheHints {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <u32 as serde::Deserialize>::deserialize(deserializer).map(CacheHints::from_bits_truncate)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Ownership of the buffers of a stream at an instant
+///
+/// Only queued buffers can be filled by the driver. If the consumer holds on to too many
+/// dequeued buffers, the driver runs out of buffers and drops frames.
+pub struct QueueState {
+    /// Buffers owned by the driver, which are waiting to be filled or hold frames waiting to be
+    /// dequeued
+    pub queued: usize,
+    /// Buffers owned by the consumer
+    pub dequeued: usize,
+    /// Total number of buffers
+    pub total: usize,
+}
+
+bitflags! {
+    /// Cache maintenance hints for stream buffers
+    ///
+    /// On platforms without cache coherent DMA (e.g. many ARM SoCs), the kernel cleans the CPU
+    /// caches when a buffer is queued and invalidates them when it is dequeued, which is always
+    /// safe but not free. Skipping either step is only correct if the application itself makes
+    /// sure the CPU does not see stale data, otherwise frames may show tearing artifacts.
+    pub struct CacheHints: u32 {
+        /// Perform full cache maintenance
+        const NONE                  = 0x000;
+        /// Do not invalidate the caches when a buffer is dequeued
+        const NO_INVALIDATE         = 0x001;
+        /// Do not clean the caches when a buffer is queued
+        const NO_CLEAN              = 0x002;
+    }
 }
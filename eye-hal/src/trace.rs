@@ -0,0 +1,29 @@
+//! Optional instrumentation
+//!
+//! When the `tracing` feature is enabled, the HAL emits events for device handling, format
+//! negotiation and the buffer lifecycle through the `tracing` crate. Add a subscriber such as
+//! `tracing-subscriber` to your application to see them.
+//!
+//! Without the feature, the macros in this module expand to nothing and the instrumentation is
+//! compiled out entirely.
+
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::trace!($($arg)+);
+    };
+}
+
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)+);
+    };
+}
+
+macro_rules! error {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::error!($($arg)+);
+    };
+}
@@ -1,16 +1,87 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::buffer::Buffer;
 use crate::control;
 use crate::device;
-use crate::error::Result;
+use crate::error::{Error, ErrorKind, Result};
+use crate::format::{ImageFormat, PixelFormat};
+use crate::geometry::Rect;
 use crate::platform::{Device as PlatformDevice, Stream as PlatformStream};
 use crate::stream;
 
 /// Platform context abstraction
 pub trait Context {
     /// Returns all devices currently available
+    ///
+    /// Contexts which keep a device cache return the devices found by the last
+    /// [`Context::refresh`] call.
     fn devices(&self) -> Result<Vec<device::Description>>;
 
+    /// Returns the first available device
+    ///
+    /// Returns an error of kind [`ErrorKind::NoDevices`] instead of an empty list, which is
+    /// convenient for applications which just use whatever camera is there.
+    fn first_device(&self) -> Result<device::Description> {
+        self.devices()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::new(ErrorKind::NoDevices, "no devices available"))
+    }
+
+    /// Returns the devices matching a predicate
+    ///
+    /// This saves enumerating all devices just to filter them, which is expensive for contexts
+    /// which open every device to describe it. Such contexts pass the predicate a device whose
+    /// properties are queried on demand instead, e.g.:
+    ///
+    /// ```no_run
+    /// # use eye_hal::device::Capabilities;
+    /// # use eye_hal::traits::Context;
+    /// # fn example(ctx: &impl Context) -> eye_hal::Result<()> {
+    /// let devices = ctx.find_devices(|info| {
+    ///     info.product().contains("C920")
+    ///         && info.capabilities().contains(Capabilities::VIDEO_CAPTURE)
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn find_devices<F>(&self, mut pred: F) -> Result<Vec<device::Description>>
+    where
+        F: FnMut(&dyn device::Info) -> bool,
+        Self: Sized,
+    {
+        Ok(self
+            .devices()?
+            .into_iter()
+            .filter(|desc| pred(desc))
+            .collect())
+    }
+
+    /// Updates the cached device list
+    ///
+    /// This is a no-op for contexts which enumerate the devices on every call to
+    /// [`Context::devices`].
+    fn refresh(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     /// Opens a device handle
     fn open_device<'a>(&self, uri: &str) -> Result<PlatformDevice<'a>>;
+
+    /// Estimates whether streams of several devices fit on their USB buses
+    ///
+    /// Takes (device URI, stream) pairs. Running more uncompressed video over a bus than it can
+    /// carry shows up as dropped or corrupt frames rather than as an error, so check the report
+    /// before starting the streams. Contexts which cannot tell the bus of a device assume all
+    /// devices share a single USB 2.0 bus.
+    fn estimate_bandwidth(
+        &self,
+        streams: &[(&str, &stream::Descriptor)],
+    ) -> device::BandwidthReport {
+        let streams: Vec<_> = streams.iter().map(|(_, desc)| (None, *desc)).collect();
+        device::BandwidthReport::new(&streams)
+    }
 }
 
 /// Platform device abstraction
@@ -18,20 +89,509 @@ pub trait Device<'a> {
     /// Returns the supported streams
     fn streams(&self) -> Result<Vec<stream::Descriptor>>;
 
+    /// Returns the streams which actually deliver frames
+    ///
+    /// Some drivers advertise formats which they cannot stream. This starts each stream in turn
+    /// and keeps the ones which produce a frame within `timeout`, so it takes a while for devices
+    /// with many formats. Cameras may take a moment to deliver the first frame after starting a
+    /// stream, so the timeout should be generous (e.g. a second).
+    fn probe_streams(&self, timeout: Duration) -> Result<Vec<stream::Descriptor>> {
+        let mut working = Vec::new();
+        for desc in self.streams()? {
+            let settings = stream::DeviceStreamSettings::new(&desc).with_timeout(timeout);
+            let mut stream = match self.start_stream(settings) {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            if let Some(Ok(_)) = stream.next() {
+                working.push(desc.clone());
+            }
+        }
+
+        Ok(working)
+    }
+
+    /// Returns the formats reserved for still image capture
+    ///
+    /// UVC cameras may advertise still image formats separately from their video formats, often
+    /// at a higher resolution than any video mode. Returns `ErrorKind::NotSupported` if the
+    /// device has no dedicated still image formats or the platform does not expose them (V4L2
+    /// only reports the video formats).
+    fn still_streams(&self) -> Result<Vec<stream::Descriptor>> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Returns whether the exact stream configuration is supported
+    ///
+    /// Unlike [`Device::start_stream`], this does not alter the device state.
+    fn supports(&self, desc: &stream::Descriptor) -> bool {
+        let streams = match self.streams() {
+            Ok(streams) => streams,
+            Err(_) => return false,
+        };
+
+        // Intervals are usually computed from fractions, so allow for rounding errors.
+        streams.iter().any(|stream| {
+            stream.width == desc.width
+                && stream.height == desc.height
+                && stream.pixfmt == desc.pixfmt
+                && stream.interval.max(desc.interval) - stream.interval.min(desc.interval)
+                    < Duration::from_micros(1)
+        })
+    }
+
+    /// Returns the pixel formats offered at a frame size
+    ///
+    /// Devices often offer different formats depending on the resolution, e.g. MJPEG at high
+    /// resolutions but only uncompressed formats at low ones. Each format is listed once, in the
+    /// order of [`Device::streams`].
+    fn formats_at(&self, width: u32, height: u32) -> Result<Vec<PixelFormat>> {
+        let mut formats = Vec::new();
+        for desc in self.streams()? {
+            if desc.width == width && desc.height == height && !formats.contains(&desc.pixfmt) {
+                formats.push(desc.pixfmt);
+            }
+        }
+
+        Ok(formats)
+    }
+
+    /// Returns whether the streams can be running at the same time
+    ///
+    /// Devices with multiple streams may share resources between them (e.g. an ISP which feeds
+    /// both a preview and a still capture stream), so not every combination of supported stream
+    /// configurations is valid.
+    ///
+    /// None of the backends can query these constraints from the hardware yet, so this is a
+    /// conservative heuristic: a single supported stream is accepted, any combination of
+    /// multiple streams is rejected. A `true` result does not guarantee that starting the streams
+    /// succeeds, but a `false` one may be wrong for capable hardware.
+    fn supports_concurrent(&self, descs: &[stream::Descriptor]) -> bool {
+        match descs {
+            [] => true,
+            [desc] => self.supports(desc),
+            _ => false,
+        }
+    }
+
     /// Returns a stream which produces images
+    ///
+    /// Fails with [`ErrorKind::FormatNotSupportedAtSize`] if the driver substitutes a different
+    /// frame size or pixel format for the requested one.
     fn start_stream(
         &self,
         settings: stream::DeviceStreamSettings<'_>,
     ) -> Result<PlatformStream<'a>>;
 
+    /// Returns two streams which are fed from the same sensor capture
+    ///
+    /// Some ISPs can output a frame at multiple scales at once. The resulting streams produce
+    /// images of the same instant, so buffers with the same sequence number belong together.
+    ///
+    /// Returns `ErrorKind::NotSupported` if the hardware cannot split a capture. Consider using
+    /// a software downscaled preview instead in that case.
+    fn start_dual_stream(
+        &self,
+        _main: stream::DeviceStreamSettings<'_>,
+        _secondary: stream::DeviceStreamSettings<'_>,
+    ) -> Result<(PlatformStream<'a>, PlatformStream<'a>)> {
+        Err(Error::new(
+            ErrorKind::NotSupported,
+            "device cannot split a capture into multiple streams",
+        ))
+    }
+
+    /// Captures a single frame at the highest resolution available for a pixel format
+    ///
+    /// Many cameras need a few frames to settle their auto exposure and white balance, so the
+    /// first frames are usually too dark. The stream is started, the given number of frames are
+    /// skipped and the next frame is returned. The stream is stopped afterwards.
+    ///
+    /// Returns the frame along with the descriptor of the stream that was used.
+    fn capture_still(
+        &self,
+        pixfmt: PixelFormat,
+        warmup: usize,
+    ) -> Result<(Buffer<'static>, stream::Descriptor)> {
+        let desc = stream::Descriptors::from(self.streams()?)
+            .with_format(pixfmt)
+            .max_by_resolution()
+            .ok_or_else(|| Error::new(ErrorKind::NotSupported, "no stream for pixel format"))?;
+
+        let mut stream = self.start_stream(stream::DeviceStreamSettings::new(&desc))?;
+        for _ in 0..warmup {
+            match stream.next() {
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e),
+                None => break,
+            }
+        }
+
+        match stream.next() {
+            Some(Ok(buf)) => Ok((buf.own(), desc)),
+            Some(Err(e)) => Err(e),
+            None => Err(Error::new(ErrorKind::Other, "stream ended")),
+        }
+    }
+
+    /// Returns the format the device is currently configured for
+    ///
+    /// This only reads the configuration, so it is safe to call on a handle to a device which is
+    /// streaming from another handle or process (e.g. to monitor a capture) and does not disturb
+    /// the active stream.
+    fn current_format(&self) -> Result<ImageFormat> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Captures one frame per interval, stopping the stream in between
+    ///
+    /// Keeping a stream running to use only a fraction of its frames wastes power and heats up
+    /// the sensor. Instead, a stream is started for every frame, the given number of frames are
+    /// skipped so auto exposure and white balance settle, and the next frame is passed to `sink`.
+    /// The stream is stopped before waiting for the next cycle. Intervals are measured from the
+    /// start of one cycle to the start of the next one.
+    ///
+    /// Runs until `sink` returns false or an error occurs.
+    fn timelapse<F>(
+        &self,
+        desc: &stream::Descriptor,
+        interval: Duration,
+        warmup: usize,
+        mut sink: F,
+    ) -> Result<()>
+    where
+        F: FnMut(Buffer) -> bool,
+        Self: Sized,
+    {
+        loop {
+            let start = Instant::now();
+
+            let mut stream = self.start_stream(stream::DeviceStreamSettings::new(desc))?;
+            for _ in 0..warmup {
+                match stream.next() {
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e),
+                    None => break,
+                }
+            }
+            let proceed = match stream.next() {
+                Some(Ok(buf)) => sink(buf),
+                Some(Err(e)) => return Err(e),
+                None => return Err(Error::new(ErrorKind::Other, "stream ended")),
+            };
+            drop(stream);
+            if !proceed {
+                return Ok(());
+            }
+
+            thread::sleep(interval.saturating_sub(start.elapsed()));
+        }
+    }
+
+    /// Captures a burst of frames into memory
+    ///
+    /// This is meant for short captures at high frame rates (e.g. for offline motion analysis),
+    /// where no frame may be lost. The memory for all frames is allocated before the stream is
+    /// started and the stream uses a deep buffer pool, so the only work per frame is copying it
+    /// before its buffer is queued again.
+    ///
+    /// Fails if the frame sequence numbers reveal that the driver dropped frames during the
+    /// burst.
+    fn capture_burst(
+        &self,
+        desc: &stream::Descriptor,
+        count: usize,
+    ) -> Result<Vec<Buffer<'static>>> {
+        // Enough buffers for a quarter of a second absorb the occasional hiccup of the consumer.
+        let buffers = ((desc.fps() / 4.0).ceil() as usize).clamp(4, 32);
+        let settings = stream::DeviceStreamSettings::new(desc)
+            .with_buffers_count(buffers)
+            .with_consume_policy(stream::ConsumePolicy::Fifo);
+
+        // The size of compressed frames is not known upfront, their storage grows on the first
+        // copy.
+        let size = desc.frame_bytes().unwrap_or(0);
+        let storage: Vec<Vec<u8>> = (0..count).map(|_| Vec::with_capacity(size)).collect();
+        let mut frames = Vec::with_capacity(count);
+
+        let mut stream = self.start_stream(settings)?;
+        let mut expected: Option<u32> = None;
+        for mut data in storage {
+            let meta = match stream.next() {
+                Some(Ok(buf)) => {
+                    data.extend_from_slice(buf.as_bytes());
+                    *buf.meta()
+                }
+                Some(Err(e)) => return Err(e),
+                None => return Err(Error::new(ErrorKind::Other, "stream ended")),
+            };
+
+            if let (Some(expected), Some(sequence)) = (expected, meta.sequence) {
+                if sequence != expected {
+                    return Err(Error::new(
+                        ErrorKind::Other,
+                        format!(
+                            "{} frames were dropped during the burst",
+                            sequence.wrapping_sub(expected)
+                        ),
+                    ));
+                }
+            }
+            expected = meta.sequence.map(|sequence| sequence.wrapping_add(1));
+            frames.push(Buffer::from(data).with_meta(meta));
+        }
+
+        Ok(frames)
+    }
+
+    /// Returns the cropping capabilities, e.g. the full sensor area
+    fn crop_caps(&self) -> Result<device::CropCaps> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Returns the area of the sensor which is currently captured
+    fn crop(&self) -> Result<Rect> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Selects the area of the sensor to capture, returns the area the driver actually chose
+    ///
+    /// Drivers adjust the rectangle to their constraints, e.g. to keep it within
+    /// [`CropCaps::bounds`](device::CropCaps::bounds). Some drivers support changing the area
+    /// while streaming, which is cheap enough to do for every frame (e.g. for electronic image
+    /// stabilization).
+    fn set_crop(&mut self, _rect: Rect) -> Result<Rect> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
     /// Returns the supported controls
     fn controls(&self) -> Result<Vec<control::Descriptor>>;
 
     /// Returns the current control value for an ID
     fn control(&self, id: u32) -> Result<control::State>;
 
+    /// Returns the current, default, minimum or maximum control value for an ID
+    ///
+    /// Unlike the values in the control descriptors, these are read from the driver on every
+    /// call.
+    fn control_which(&self, id: u32, which: control::Which) -> Result<control::State> {
+        match which {
+            control::Which::Current => self.control(id),
+            _ => Err(Error::from(ErrorKind::NotSupported)),
+        }
+    }
+
+    /// Returns the native ID of a control
+    ///
+    /// Returns `ErrorKind::NotSupported` if the device does not have the control.
+    fn control_id(&self, id: control::ControlId) -> Result<u32> {
+        match id {
+            control::ControlId::Raw(id) => Ok(id),
+            _ => Err(Error::from(ErrorKind::NotSupported)),
+        }
+    }
+
     /// Sets the control value, returns error for incompatible value types
     fn set_control(&mut self, id: u32, val: &control::State) -> Result<()>;
+
+    /// Saves the current values of all readable and writable controls
+    ///
+    /// Controls whose value cannot be read (e.g. because they are inactive) are left out.
+    fn snapshot_controls(&self) -> Result<control::Snapshot> {
+        let mut snapshot = control::Snapshot::default();
+        for desc in self.controls()? {
+            if !desc.readable() || !desc.writable() {
+                continue;
+            }
+
+            match self.control(desc.id) {
+                Ok(control::State::None) | Err(_) => continue,
+                Ok(value) => snapshot.values.push(control::SnapshotEntry {
+                    id: desc.id,
+                    name: desc.name,
+                    value,
+                }),
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Applies the control values of a snapshot
+    ///
+    /// Values are applied in the order of the snapshot. Applying does not stop at the first
+    /// failure. Instead, all values which could not be applied, e.g. because the device does not
+    /// have the control, are returned.
+    fn apply_snapshot(&mut self, snapshot: &control::Snapshot) -> Result<Vec<control::Mismatch>> {
+        let controls = self.controls()?;
+        let mut mismatches = Vec::new();
+        for entry in &snapshot.values {
+            let mismatch = |error| control::Mismatch {
+                id: entry.id,
+                name: entry.name.clone(),
+                error,
+            };
+
+            match controls.iter().find(|desc| desc.id == entry.id) {
+                None => mismatches.push(mismatch(Error::new(
+                    ErrorKind::NotSupported,
+                    "no such control",
+                ))),
+                Some(desc) if !desc.writable() => mismatches.push(mismatch(Error::new(
+                    ErrorKind::NotSupported,
+                    "control is not writable",
+                ))),
+                Some(_) => {
+                    if let Err(e) = self.set_control(entry.id, &entry.value) {
+                        mismatches.push(mismatch(e));
+                    }
+                }
+            }
+        }
+
+        Ok(mismatches)
+    }
+
+    /// Returns the current values of all read-only controls, e.g. sensor temperatures
+    ///
+    /// Read-only controls usually report telemetry rather than settings, so this is convenient
+    /// for monitoring a device. Controls whose value cannot be read at the moment are left out.
+    fn telemetry(&self) -> Result<Vec<(String, control::State)>> {
+        let mut values = Vec::new();
+        for desc in self.controls()? {
+            if !desc.readable() || desc.writable() || matches!(desc.typ, control::Type::Stateless) {
+                continue;
+            }
+
+            if let Ok(value) = self.control(desc.id) {
+                values.push((desc.name, value));
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Returns the power line frequency the device compensates for
+    fn power_line_frequency(&self) -> Result<control::PowerLineFrequency> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Sets the power line frequency the device compensates for
+    ///
+    /// Devices expose the anti-flicker control in different ways (e.g. as a menu with varying
+    /// items or as a plain number), so this resolves the actual representation of the control
+    /// instead of requiring callers to know the raw value.
+    fn set_power_line_frequency(&mut self, _freq: control::PowerLineFrequency) -> Result<()> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Sets how the lens is focused
+    ///
+    /// [`FocusMode::Auto`](control::FocusMode::Auto) triggers a single focus run, after which the
+    /// lens stays where it is. Switching to manual mode keeps the current lens position.
+    fn set_focus_mode(&mut self, _mode: control::FocusMode) -> Result<()> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Moves the lens to an absolute position and switches to manual focus
+    ///
+    /// The unit is device specific, greater values usually focus farther away. The position is
+    /// clamped to the range of the device. The position cannot be set while automatic focus is
+    /// active, so it is turned off first.
+    fn set_focus_absolute(&mut self, _value: i64) -> Result<()> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Returns the current lens position, see [`Self::set_focus_absolute`]
+    ///
+    /// Most devices report the position chosen by the automatic focus as well.
+    fn focus_distance(&self) -> Result<i64> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Restricts the range of distances the automatic focus searches
+    fn set_focus_range(&mut self, _range: control::FocusRange) -> Result<()> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Returns whether analog and digital gain can be controlled independently
+    fn gain_split_supported(&self) -> bool {
+        false
+    }
+
+    /// Sets the analog gain in driver specific units
+    ///
+    /// Devices which only offer a combined gain control set that one instead, which usually
+    /// applies analog gain first.
+    fn set_analog_gain(&mut self, _value: f64) -> Result<()> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Sets the digital gain in driver specific units
+    ///
+    /// Unlike [`Device::set_analog_gain`], this does not fall back to a combined gain control.
+    fn set_digital_gain(&mut self, _value: f64) -> Result<()> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Locks the values currently chosen by the automatic exposure, white balance and gain
+    ///
+    /// Let the automatic algorithms converge first (e.g. by capturing a couple of frames), then
+    /// call this to keep the image characteristics constant for the rest of the session. The
+    /// resolved values are read back and the controls are switched to manual mode with these
+    /// values. Controls the device does not have are left alone.
+    fn lock_auto_values(&mut self) -> Result<()> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Sets the region of the image the automatic algorithms (e.g. exposure) are based on
+    ///
+    /// Metering on a region helps with subjects which are not centered or in front of a bright
+    /// background. The rectangle is given in pixels of the current format, the mode selects the
+    /// algorithms which consider it. Devices pack the region into their controls in different
+    /// ways, this uses the convention of the UVC region of interest control.
+    fn set_metering_roi(&mut self, _rect: Rect, _mode: control::MeteringMode) -> Result<()> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Queries a vendor specific control of a UVC extension unit
+    ///
+    /// Extension units are addressed by their unit ID and each of their controls by a selector,
+    /// both of which are documented by the vendor (or can be found in the USB descriptors). The
+    /// data is read into or written from `data`, whose size must match the size of the control
+    /// (see [`control::XuQuery::GetLen`]).
+    fn uvc_xu_query(
+        &mut self,
+        _unit: u8,
+        _selector: u8,
+        _query: control::XuQuery,
+        _data: &mut [u8],
+    ) -> Result<()> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Returns the capabilities reported by the driver
+    ///
+    /// E.g. devices without [`STREAMING`](device::Capabilities::STREAMING) support can only be
+    /// read from like a file.
+    fn capabilities(&self) -> Result<device::Capabilities> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Returns the audio inputs of the device
+    ///
+    /// The audio itself has to be captured through the platform audio API, using the device
+    /// reported for each input.
+    fn audio_inputs(&self) -> Result<Vec<device::AudioInput>> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Asks the driver to write its internal state to the system log
+    ///
+    /// This is useful to debug driver issues. On Linux, the output ends up in the kernel log.
+    fn log_status(&self) -> Result<()> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
 }
 
 /// Stream abstraction
@@ -44,4 +604,94 @@ pub trait Stream<'a> {
 
     /// Advances the stream and returns the next item
     fn next(&'a mut self) -> Option<Self::Item>;
+
+    /// Returns the number of buffers actually allocated for the stream
+    ///
+    /// Drivers may grant fewer (or more) buffers than requested in the stream settings. Returns
+    /// `None` if the implementation does not know about its buffers.
+    fn buffer_count(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the driver index of the buffer which holds the frame returned last
+    ///
+    /// This allows correlating frames with other driver interfaces (e.g. events) which refer to
+    /// buffers by index. Returns `None` if no frame is held or the implementation does not map
+    /// its frames to driver buffers.
+    fn last_buffer_index(&self) -> Option<u32> {
+        None
+    }
+
+    /// Returns how many buffers are currently queued to the driver and held by the consumer
+    ///
+    /// Returns `None` if the implementation does not know about its buffers.
+    fn queue_state(&self) -> Option<stream::QueueState> {
+        None
+    }
+
+    /// Returns the instant at which the device last started streaming
+    ///
+    /// Streams start when the first frame is requested and restart after they were resumed or
+    /// reconfigured. Returns `None` if the stream has not started yet or the implementation does
+    /// not know when it started.
+    fn start_instant(&self) -> Option<Instant> {
+        None
+    }
+
+    /// Returns the time from the start of streaming until the first frame was captured
+    ///
+    /// Together with the time it took to request the first frame, this splits the startup latency
+    /// into starting the device and waiting for the sensor. The capture time is taken from the
+    /// timestamp of the first frame returned after the start, so discarded warmup frames count
+    /// towards the latency. Returns `None` until that frame arrived or if the implementation does
+    /// not know about it.
+    fn first_frame_latency(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Returns the effective stream configuration
+    ///
+    /// Drivers may adjust the requested configuration (e.g. snap the frame size to a supported
+    /// one), so this can differ from the descriptor the stream was started with. Returns `None`
+    /// if the implementation does not know about its configuration.
+    fn descriptor(&self) -> Option<stream::Descriptor> {
+        None
+    }
+
+    /// Changes the frame interval of a running stream
+    ///
+    /// The buffers of the stream are kept, so this is much cheaper than starting a new stream.
+    /// Drivers may adjust the requested interval, [`Stream::descriptor`] reports the one which is
+    /// in effect.
+    fn set_interval(&mut self, _interval: Duration) -> Result<()> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Reallocates the buffers of the stream
+    ///
+    /// Fewer buffers reduce the latency, more buffers allow the consumer to fall behind for a
+    /// while without dropping frames. A running stream is stopped and restarted once the next
+    /// item is requested. Use [`Stream::buffer_count`] to retrieve the number of buffers which
+    /// were actually allocated.
+    fn set_buffer_count(&mut self, _count: usize) -> Result<()> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Pauses the capture, but keeps the buffers allocated
+    ///
+    /// A paused stream returns `None` from [`Stream::next`] until it is resumed, use
+    /// [`Stream::is_paused`] to tell it apart from a stream which ended.
+    fn pause(&mut self) -> Result<()> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Resumes a paused capture
+    fn resume(&mut self) -> Result<()> {
+        Err(Error::from(ErrorKind::NotSupported))
+    }
+
+    /// Returns whether the stream is paused
+    fn is_paused(&self) -> bool {
+        false
+    }
 }
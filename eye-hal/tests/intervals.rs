@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use eye_hal::format::PixelFormat;
+use eye_hal::geometry::Fraction;
+use eye_hal::stream::Descriptor;
+
+fn descriptor() -> Descriptor {
+    Descriptor {
+        width: 640,
+        height: 480,
+        pixfmt: PixelFormat::Yuyv,
+        interval: Duration::from_millis(33),
+        emulated: false,
+    }
+}
+
+#[test]
+fn fraction_from_duration() {
+    assert_eq!(
+        Fraction::from(Duration::from_millis(40)),
+        Fraction::new(1, 25)
+    );
+    assert_eq!(
+        Fraction::from(Duration::from_millis(33)),
+        Fraction::new(33, 1000)
+    );
+    // NTSC intervals, rounded to nanoseconds
+    assert_eq!(
+        Fraction::from(Duration::from_nanos(33_366_667)),
+        Fraction::new(1001, 30000)
+    );
+    assert_eq!(
+        Fraction::from(Duration::from_nanos(16_683_333)),
+        Fraction::new(1001, 60000)
+    );
+}
+
+#[test]
+fn ntsc_frame_rates() {
+    let desc = descriptor().with_fps(29.97);
+    assert_eq!(desc.interval_fraction(), Fraction::new(1001, 30000));
+    assert!((desc.fps() - 30000.0 / 1001.0).abs() < 1e-4);
+
+    let desc = descriptor().with_fps(59.94);
+    assert_eq!(desc.interval_fraction(), Fraction::new(1001, 60000));
+    assert!((desc.fps() - 60000.0 / 1001.0).abs() < 1e-4);
+}
+
+#[test]
+fn integer_frame_rates() {
+    for fps in [1, 15, 25, 30, 60, 120] {
+        let desc = descriptor().with_fps(fps as f64);
+        assert_eq!(desc.interval_fraction(), Fraction::new(1, fps));
+    }
+}
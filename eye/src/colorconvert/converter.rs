@@ -0,0 +1,116 @@
+use eye_hal::buffer::{Buffer, Metadata};
+use eye_hal::error::{Error, ErrorKind, Result};
+use eye_hal::format::{ImageFormat, PixelFormat};
+use eye_hal::traits::Stream;
+
+use crate::colorconvert::Pipeline;
+
+/// A stream whose frames are converted into a caller provided buffer
+///
+/// Unlike a converting [`Device`](crate::colorconvert::Device) stream, which wraps every frame
+/// in a new buffer, [`Converter::next_converted`] writes the converted frame into an existing
+/// vector without an intermediate copy. The conversion pipeline is set up once and rebuilt only
+/// when the target format or the stream format changes.
+///
+/// The allocation of the vector is reused when no conversion is needed. Otherwise, the codec
+/// allocates the converted image and it replaces the contents of the vector, see [`Pipeline`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use eye::hal::{platform, Result};
+/// # fn example(stream: platform::Stream) -> Result<()> {
+/// use eye::colorconvert::Converter;
+/// use eye::hal::format::PixelFormat;
+///
+/// let mut converter = Converter::new(stream);
+/// let mut rgb = Vec::new();
+/// while let Some(meta) = converter.next_converted(PixelFormat::Rgb(24), &mut rgb) {
+///     let meta = meta?;
+///     println!("frame {:?}: {} bytes", meta.sequence, rgb.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Converter<S> {
+    inner: S,
+    pipeline: Option<Pipeline>,
+}
+
+impl<S> Converter<S> {
+    /// Creates a new converter for the frames of `inner`
+    ///
+    /// The stream must know its [`descriptor`](Stream::descriptor), the source format is taken
+    /// from there.
+    pub fn new(inner: S) -> Self {
+        Converter {
+            inner,
+            pipeline: None,
+        }
+    }
+
+    /// Returns the wrapped stream
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> Converter<S>
+where
+    S: for<'a> Stream<'a, Item = Result<Buffer<'a>>>,
+{
+    /// Reads the next frame and writes it to `out`, converted to the given pixel format
+    ///
+    /// The contents of `out` are replaced. Returns the metadata of the frame, or `None` once the
+    /// stream ended.
+    pub fn next_converted(
+        &mut self,
+        to: PixelFormat,
+        out: &mut Vec<u8>,
+    ) -> Option<Result<Metadata>> {
+        // set up the pipeline first, the frame borrows the stream
+        if let Err(e) = self.prepare(to) {
+            return Some(Err(e));
+        }
+
+        let frame = match self.inner.next()? {
+            Ok(frame) => frame,
+            Err(e) => return Some(Err(e)),
+        };
+        let pipeline = self.pipeline.as_mut()?;
+        if let Err(e) = pipeline.process(&frame, out) {
+            return Some(Err(e));
+        }
+
+        Some(Ok(*frame.meta()))
+    }
+
+    /// Builds a pipeline for the current stream format, unless the existing one fits
+    fn prepare(&mut self, to: PixelFormat) -> Result<()> {
+        let desc = self
+            .inner
+            .descriptor()
+            .ok_or_else(|| Error::new(ErrorKind::NotSupported, "stream format is not known"))?;
+        let input = ImageFormat::new(desc.width, desc.height, desc.pixfmt);
+
+        let fits = |pipeline: &Pipeline| {
+            let from = pipeline.input_format();
+            from.width == input.width
+                && from.height == input.height
+                && from.pixfmt == input.pixfmt
+                && pipeline.output_format().pixfmt == to
+        };
+        if matches!(&self.pipeline, Some(pipeline) if fits(pipeline)) {
+            return Ok(());
+        }
+
+        let builder = Pipeline::builder(input.clone());
+        let pipeline = if input.pixfmt == to {
+            builder.build()
+        } else {
+            builder.convert(to)?.build()
+        };
+        self.pipeline = Some(pipeline);
+        Ok(())
+    }
+}
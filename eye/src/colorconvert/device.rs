@@ -1,21 +1,35 @@
+use std::{
+    mem,
+    sync::{Arc, Mutex},
+};
+
 use eye_hal::error::{Error, ErrorKind, Result};
+use eye_hal::format::ImageFormat;
+use eye_hal::geometry::Rect;
 use eye_hal::platform::Context as PlatformContext;
 use eye_hal::platform::{Device as PlatformDevice, Stream as PlatformStream};
 use eye_hal::traits::{Context, Device as DeviceTrait};
-use eye_hal::{control, stream};
+use eye_hal::{control, device, stream};
 
 use crate::colorconvert::codec;
-use crate::colorconvert::stream::CodecStream;
+use crate::colorconvert::stream::{CodecStream, ScaledStream};
+use crate::colorconvert::Pipeline;
+use crate::preview::{self, PreviewStream};
 
 /// A transparent wrapper type for native platform devices.
 pub struct Device<'a> {
     // actual platform device implementation
     inner: PlatformDevice<'a>,
+    // preview streams waiting for the next stream to be started
+    previews: Mutex<Vec<Arc<preview::Shared>>>,
 }
 
 impl<'a> Device<'a> {
     pub fn new(dev: PlatformDevice<'a>) -> Result<Self> {
-        Ok(Device { inner: dev })
+        Ok(Device {
+            inner: dev,
+            previews: Mutex::new(Vec::new()),
+        })
     }
 
     pub fn with_uri<S: AsRef<str>>(uri: S) -> Result<Self> {
@@ -25,53 +39,82 @@ impl<'a> Device<'a> {
 
         Self::new(inner)
     }
-}
-
-impl<'a> DeviceTrait<'a> for Device<'a> {
-    fn streams(&self) -> Result<Vec<stream::Descriptor>> {
-        // get all the native streams
-        let mut streams = self.inner.streams()?;
 
-        // now check which formats we can emulate
-        for blueprint in codec::blueprints() {
-            for chain in blueprint.src_fmts().iter().zip(blueprint.dst_fmts().iter()) {
-                if streams
-                    .iter()
-                    .find(|stream| stream.pixfmt == *chain.0)
-                    .is_some()
-                    && streams
-                        .iter()
-                        .find(|stream| stream.pixfmt == *chain.1)
-                        .is_none()
-                {
-                    // collect all streams with this pixfmt
-                    let _streams: Vec<stream::Descriptor> = streams
-                        .iter()
-                        .filter_map(|stream| {
-                            if stream.pixfmt == *chain.0 {
-                                Some(stream.clone())
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
+    /// Returns a stream of downscaled frames
+    ///
+    /// The preview stream is attached to the next stream started on this device and receives a
+    /// copy of each of its frames, downscaled by `scale` (e.g. 0.25 for a quarter of the width and
+    /// height). Starting the full resolution stream fails if its frames cannot be downscaled.
+    ///
+    /// # Arguments
+    ///
+    /// * `scale` - Scale factor in the range of (0, 1]
+    pub fn start_preview_stream(&self, scale: f32) -> Result<PreviewStream> {
+        let stream = PreviewStream::new(scale)?;
+        self.previews.lock().unwrap().push(stream.shared());
+        Ok(stream)
+    }
 
-                    _streams.into_iter().for_each(|stream| {
-                        streams.push(stream::Descriptor {
-                            width: stream.width,
-                            height: stream.height,
-                            pixfmt: chain.1.clone(),
-                            interval: stream.interval,
-                        });
-                    });
-                }
-            }
+    /// Opens a stream of the requested size, scaling down a larger size if necessary
+    fn open_scaled_stream(
+        &self,
+        settings: stream::DeviceStreamSettings,
+    ) -> Result<PlatformStream<'a>> {
+        let desc = settings.desc;
+        let streams = self.streams()?;
+        let offered = streams.iter().any(|stream| {
+            stream.pixfmt == desc.pixfmt
+                && stream.width == desc.width
+                && stream.height == desc.height
+        });
+        if offered {
+            return self.open_stream(settings);
         }
 
-        Ok(streams)
+        // The smallest of the larger sizes needs the least scaling, prefer the requested interval
+        // among its streams.
+        let source = streams
+            .into_iter()
+            .filter(|stream| {
+                stream.pixfmt == desc.pixfmt
+                    && stream.width >= desc.width
+                    && stream.height >= desc.height
+            })
+            .min_by_key(|stream| {
+                (
+                    stream.size().area(),
+                    stream.interval.max(desc.interval) - stream.interval.min(desc.interval),
+                )
+            })
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotSupported,
+                    format!(
+                        "no {} stream of at least {}x{}",
+                        desc.pixfmt, desc.width, desc.height
+                    ),
+                )
+            })?;
+
+        let pipeline = Pipeline::builder(ImageFormat::new(
+            source.width,
+            source.height,
+            source.pixfmt.clone(),
+        ))
+        .resize(desc.width, desc.height)?
+        .build();
+        let inner = self.open_stream(stream::DeviceStreamSettings {
+            desc: &source,
+            ..settings
+        })?;
+
+        Ok(PlatformStream::Custom(Box::new(ScaledStream {
+            inner,
+            pipeline,
+        })))
     }
 
-    fn start_stream(&self, settings: stream::DeviceStreamSettings) -> Result<PlatformStream<'a>> {
+    fn open_stream(&self, settings: stream::DeviceStreamSettings) -> Result<PlatformStream<'a>> {
         let desc = settings.desc;
         let native_streams = self.inner.streams()?;
         if let Some(_) = native_streams
@@ -151,15 +194,107 @@ impl<'a> DeviceTrait<'a> for Device<'a> {
         source_fmt.pixfmt = src_fmt;
         let native_stream = self.inner.start_stream(stream::DeviceStreamSettings {
             desc: &source_fmt,
-            buffers_count: settings.buffers_count.clone(),
+            ..settings
         })?;
 
         // create the instance that converts the frames for us
         return Ok(PlatformStream::Custom(Box::new(CodecStream {
             inner: native_stream,
             codec,
+            pixfmt: desc.pixfmt.clone(),
         })));
     }
+}
+
+impl<'a> DeviceTrait<'a> for Device<'a> {
+    fn streams(&self) -> Result<Vec<stream::Descriptor>> {
+        // get all the native streams
+        let mut streams = self.inner.streams()?;
+
+        // now check which formats we can emulate
+        for blueprint in codec::blueprints() {
+            for chain in blueprint.src_fmts().iter().zip(blueprint.dst_fmts().iter()) {
+                if streams
+                    .iter()
+                    .find(|stream| stream.pixfmt == *chain.0)
+                    .is_some()
+                    && streams
+                        .iter()
+                        .find(|stream| stream.pixfmt == *chain.1)
+                        .is_none()
+                {
+                    // collect all streams with this pixfmt
+                    let _streams: Vec<stream::Descriptor> = streams
+                        .iter()
+                        .filter_map(|stream| {
+                            if stream.pixfmt == *chain.0 {
+                                Some(stream.clone())
+                            } else {
+                                None
+                            }
+                        })
+                        .collect();
+
+                    _streams.into_iter().for_each(|stream| {
+                        streams.push(stream::Descriptor {
+                            width: stream.width,
+                            height: stream.height,
+                            pixfmt: chain.1.clone(),
+                            interval: stream.interval,
+                            emulated: true,
+                        });
+                    });
+                }
+            }
+        }
+
+        Ok(streams)
+    }
+
+    fn still_streams(&self) -> Result<Vec<stream::Descriptor>> {
+        self.inner.still_streams()
+    }
+
+    fn start_stream(&self, settings: stream::DeviceStreamSettings) -> Result<PlatformStream<'a>> {
+        let desc = settings.desc.clone();
+        let stream = if settings.scale_to_requested {
+            self.open_scaled_stream(settings)?
+        } else {
+            self.open_stream(settings)?
+        };
+
+        let previews = mem::take(&mut *self.previews.lock().unwrap());
+        if previews.is_empty() {
+            return Ok(stream);
+        }
+
+        let source = preview::Source::new(stream, &desc, previews)?;
+        Ok(PlatformStream::Custom(Box::new(source)))
+    }
+
+    fn start_dual_stream(
+        &self,
+        main: stream::DeviceStreamSettings,
+        secondary: stream::DeviceStreamSettings,
+    ) -> Result<(PlatformStream<'a>, PlatformStream<'a>)> {
+        self.inner.start_dual_stream(main, secondary)
+    }
+
+    fn crop_caps(&self) -> Result<device::CropCaps> {
+        self.inner.crop_caps()
+    }
+
+    fn current_format(&self) -> Result<ImageFormat> {
+        self.inner.current_format()
+    }
+
+    fn crop(&self) -> Result<Rect> {
+        self.inner.crop()
+    }
+
+    fn set_crop(&mut self, rect: Rect) -> Result<Rect> {
+        self.inner.set_crop(rect)
+    }
 
     fn controls(&self) -> Result<Vec<control::Descriptor>> {
         self.inner.controls()
@@ -169,7 +304,81 @@ impl<'a> DeviceTrait<'a> for Device<'a> {
         self.inner.control(id)
     }
 
+    fn control_which(&self, id: u32, which: control::Which) -> Result<control::State> {
+        self.inner.control_which(id, which)
+    }
+
+    fn control_id(&self, id: control::ControlId) -> Result<u32> {
+        self.inner.control_id(id)
+    }
+
     fn set_control(&mut self, id: u32, val: &control::State) -> Result<()> {
         self.inner.set_control(id, val)
     }
+
+    fn power_line_frequency(&self) -> Result<control::PowerLineFrequency> {
+        self.inner.power_line_frequency()
+    }
+
+    fn set_power_line_frequency(&mut self, freq: control::PowerLineFrequency) -> Result<()> {
+        self.inner.set_power_line_frequency(freq)
+    }
+
+    fn set_focus_mode(&mut self, mode: control::FocusMode) -> Result<()> {
+        self.inner.set_focus_mode(mode)
+    }
+
+    fn set_focus_absolute(&mut self, value: i64) -> Result<()> {
+        self.inner.set_focus_absolute(value)
+    }
+
+    fn focus_distance(&self) -> Result<i64> {
+        self.inner.focus_distance()
+    }
+
+    fn set_focus_range(&mut self, range: control::FocusRange) -> Result<()> {
+        self.inner.set_focus_range(range)
+    }
+
+    fn gain_split_supported(&self) -> bool {
+        self.inner.gain_split_supported()
+    }
+
+    fn set_analog_gain(&mut self, value: f64) -> Result<()> {
+        self.inner.set_analog_gain(value)
+    }
+
+    fn set_digital_gain(&mut self, value: f64) -> Result<()> {
+        self.inner.set_digital_gain(value)
+    }
+
+    fn set_metering_roi(&mut self, rect: Rect, mode: control::MeteringMode) -> Result<()> {
+        self.inner.set_metering_roi(rect, mode)
+    }
+
+    fn lock_auto_values(&mut self) -> Result<()> {
+        self.inner.lock_auto_values()
+    }
+
+    fn uvc_xu_query(
+        &mut self,
+        unit: u8,
+        selector: u8,
+        query: control::XuQuery,
+        data: &mut [u8],
+    ) -> Result<()> {
+        self.inner.uvc_xu_query(unit, selector, query, data)
+    }
+
+    fn capabilities(&self) -> Result<device::Capabilities> {
+        self.inner.capabilities()
+    }
+
+    fn audio_inputs(&self) -> Result<Vec<device::AudioInput>> {
+        self.inner.audio_inputs()
+    }
+
+    fn log_status(&self) -> Result<()> {
+        self.inner.log_status()
+    }
 }
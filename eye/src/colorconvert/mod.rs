@@ -1,5 +1,9 @@
 mod codec;
+mod converter;
 mod device;
+mod pipeline;
 mod stream;
 
+pub use converter::Converter;
 pub use device::Device;
+pub use pipeline::{Pipeline, PipelineBuilder};
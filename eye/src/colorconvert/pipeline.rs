@@ -0,0 +1,295 @@
+use std::mem;
+
+use eye_hal::buffer::Buffer;
+use eye_hal::error::{Error, ErrorKind, Result};
+use eye_hal::format::{ImageFormat, PixelFormat};
+use eye_hal::geometry::Rect;
+
+use crate::colorconvert::codec::{self, Codec};
+use crate::preview;
+
+/// A reusable chain of image processing steps
+///
+/// The steps are configured once using a [`PipelineBuilder`], after which
+/// [`Pipeline::process`] runs them for each frame. Intermediate images are kept in scratch
+/// buffers owned by the pipeline and the final image is written to a buffer provided by the
+/// caller, so cropping and resizing do not allocate memory once the buffers have grown to their
+/// final size.
+///
+/// Conversion steps are the exception: the codecs return a newly allocated image, which is moved
+/// into the scratch or output buffer without copying it. The previous allocation of that buffer
+/// is dropped, so a conversion costs one allocation per frame.
+///
+/// Cropping and resizing work for uncompressed packed formats (e.g. RGB, grayscale or YUYV), so
+/// compressed frames must be converted first.
+///
+/// # Example
+///
+/// ```no_run
+/// # use eye::hal::{buffer::Buffer, Result};
+/// # fn example(buf: &Buffer) -> Result<()> {
+/// use eye::colorconvert::Pipeline;
+/// use eye::hal::format::{ImageFormat, PixelFormat};
+/// use eye::hal::geometry::Rect;
+///
+/// let mut pipeline = Pipeline::builder(ImageFormat::new(1920, 1080, PixelFormat::Jpeg))
+///     .convert(PixelFormat::Rgb(24))?
+///     .crop(Rect::new(240, 0, 1440, 1080))?
+///     .resize(640, 480)?
+///     .build();
+///
+/// let mut out = Vec::new();
+/// pipeline.process(buf, &mut out)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Pipeline {
+    input: ImageFormat,
+    stages: Vec<Stage>,
+    /// Output of the previous stage
+    cur: Vec<u8>,
+    /// Output of the current stage
+    next: Vec<u8>,
+}
+
+/// Builder for [`Pipeline`] instances
+///
+/// Each step is validated against the output format of the previous one when it is added.
+pub struct PipelineBuilder {
+    input: ImageFormat,
+    stages: Vec<Stage>,
+}
+
+enum Step {
+    Convert(Box<dyn Codec + Send>),
+    Crop(Rect),
+    Resize,
+}
+
+struct Stage {
+    step: Step,
+    from: ImageFormat,
+    to: ImageFormat,
+}
+
+impl Pipeline {
+    /// Returns a builder for a pipeline processing images of the given format
+    pub fn builder(input: ImageFormat) -> PipelineBuilder {
+        PipelineBuilder {
+            input,
+            stages: Vec::new(),
+        }
+    }
+
+    /// Returns the format of the images passed to the pipeline
+    pub fn input_format(&self) -> &ImageFormat {
+        &self.input
+    }
+
+    /// Returns the format of the images produced by the pipeline
+    pub fn output_format(&self) -> &ImageFormat {
+        self.stages
+            .last()
+            .map(|stage| &stage.to)
+            .unwrap_or(&self.input)
+    }
+
+    /// Runs all steps on a buffer and writes the result to `out`
+    ///
+    /// The contents of `out` are replaced, its allocation is reused.
+    pub fn process(&mut self, inbuf: &Buffer, out: &mut Vec<u8>) -> Result<()> {
+        let Pipeline {
+            stages, cur, next, ..
+        } = self;
+
+        if stages.is_empty() {
+            out.clear();
+            out.extend_from_slice(inbuf.as_bytes());
+            return Ok(());
+        }
+
+        for (i, stage) in stages.iter().enumerate() {
+            let src = if i == 0 { inbuf.as_bytes() } else { &cur[..] };
+            let last = i + 1 == stages.len();
+            let dst = if last { &mut *out } else { &mut *next };
+            stage.run(src, dst)?;
+
+            if !last {
+                mem::swap(cur, next);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PipelineBuilder {
+    /// Converts the images to another pixel format
+    pub fn convert(mut self, pixfmt: PixelFormat) -> Result<Self> {
+        let from = self.format().clone();
+        let blueprint = codec::blueprints()
+            .into_iter()
+            .find(|bp| bp.src_fmts().contains(&from.pixfmt) && bp.dst_fmts().contains(&pixfmt))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotSupported,
+                    format!("no codec blueprint for {} -> {}", from.pixfmt, pixfmt),
+                )
+            })?;
+
+        let inparams = codec::Parameters {
+            pixfmt: from.pixfmt.clone(),
+            width: from.width,
+            height: from.height,
+        };
+        let outparams = codec::Parameters {
+            pixfmt: pixfmt.clone(),
+            width: from.width,
+            height: from.height,
+        };
+        let codec = match blueprint.instantiate(inparams, outparams) {
+            Ok(instance) => instance,
+            Err(_e) => {
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    "failed to create codec instance",
+                ))
+            }
+        };
+
+        let to = ImageFormat::new(from.width, from.height, pixfmt);
+        self.stages.push(Stage {
+            step: Step::Convert(codec),
+            from,
+            to,
+        });
+        Ok(self)
+    }
+
+    /// Crops the images to a rectangle
+    ///
+    /// The rectangle must lie within the image. For formats which store multiple pixels in one
+    /// unit (e.g. YUYV), its left edge and width must be aligned to the unit.
+    pub fn crop(mut self, rect: Rect) -> Result<Self> {
+        let from = self.format().clone();
+        let (_, unit_pixels) = self.unit()?;
+
+        let fits = rect.left >= 0
+            && rect.top >= 0
+            && rect.width > 0
+            && rect.height > 0
+            && rect.left as u64 + rect.width as u64 <= from.width as u64
+            && rect.top as u64 + rect.height as u64 <= from.height as u64;
+        if !fits {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "crop rectangle exceeds the image",
+            ));
+        }
+        if rect.left as usize % unit_pixels != 0 || rect.width as usize % unit_pixels != 0 {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "crop rectangle is not aligned to the pixel format",
+            ));
+        }
+
+        let to = ImageFormat::new(rect.width, rect.height, from.pixfmt.clone());
+        self.stages.push(Stage {
+            step: Step::Crop(rect),
+            from,
+            to,
+        });
+        Ok(self)
+    }
+
+    /// Resizes the images using nearest neighbour sampling
+    pub fn resize(mut self, width: u32, height: u32) -> Result<Self> {
+        let from = self.format().clone();
+        let (_, unit_pixels) = self.unit()?;
+
+        if width == 0 || height == 0 || width as usize % unit_pixels != 0 {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "invalid size for the pixel format",
+            ));
+        }
+
+        let to = ImageFormat::new(width, height, from.pixfmt.clone());
+        self.stages.push(Stage {
+            step: Step::Resize,
+            from,
+            to,
+        });
+        Ok(self)
+    }
+
+    /// Returns the configured pipeline
+    pub fn build(self) -> Pipeline {
+        Pipeline {
+            input: self.input,
+            stages: self.stages,
+            cur: Vec::new(),
+            next: Vec::new(),
+        }
+    }
+
+    /// Returns the format produced by the steps added so far
+    fn format(&self) -> &ImageFormat {
+        self.stages
+            .last()
+            .map(|stage| &stage.to)
+            .unwrap_or(&self.input)
+    }
+
+    fn unit(&self) -> Result<(usize, usize)> {
+        let pixfmt = &self.format().pixfmt;
+        preview::unit(pixfmt).ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotSupported,
+                format!("cannot crop or resize {} images", pixfmt),
+            )
+        })
+    }
+}
+
+impl Stage {
+    fn run(&self, src: &[u8], dst: &mut Vec<u8>) -> Result<()> {
+        match &self.step {
+            Step::Convert(codec) => {
+                // Codecs replace the output buffer with their own, so move it into place instead
+                // of copying it.
+                let mut outbuf = Buffer::from(mem::take(dst));
+                if codec.decode(&Buffer::from(src), &mut outbuf).is_err() {
+                    return Err(Error::new(ErrorKind::Other, "failed to convert the image"));
+                }
+                *dst = outbuf.into_vec();
+            }
+            Step::Crop(rect) => {
+                // unit() succeeded when the step was added
+                let (unit_bytes, unit_pixels) = preview::unit(&self.from.pixfmt).unwrap_or((1, 1));
+                let stride = self
+                    .from
+                    .stride
+                    .unwrap_or(self.from.width as usize / unit_pixels * unit_bytes);
+                if src.len() < stride * self.from.height as usize {
+                    return Err(Error::new(ErrorKind::Other, "buffer too small"));
+                }
+
+                let offset = rect.left as usize / unit_pixels * unit_bytes;
+                let len = rect.width as usize / unit_pixels * unit_bytes;
+                dst.clear();
+                for y in rect.top as usize..rect.top as usize + rect.height as usize {
+                    let start = y * stride + offset;
+                    dst.extend_from_slice(&src[start..start + len]);
+                }
+            }
+            Step::Resize => {
+                if !preview::resize(src, &self.from, &self.to, dst) {
+                    return Err(Error::new(ErrorKind::Other, "buffer too small"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,13 +1,20 @@
+use std::time::{Duration, Instant};
+
 use eye_hal::buffer::Buffer;
 use eye_hal::error::Result;
+use eye_hal::format::PixelFormat;
+use eye_hal::stream::{Descriptor, QueueState};
 use eye_hal::traits::Stream;
 
 use crate::colorconvert::codec::Codec;
+use crate::colorconvert::Pipeline;
 
 /// A stream converting frames
 pub struct CodecStream<S> {
     pub inner: S,
     pub codec: Box<dyn Codec + Send>,
+    /// Pixel format of the converted frames
+    pub pixfmt: PixelFormat,
 }
 
 impl<'a, S> Stream<'a> for CodecStream<S>
@@ -27,6 +34,132 @@ where
         let mut outbuf = Buffer::from(Vec::new());
         self.codec.decode(&inbuf, &mut outbuf).unwrap();
 
-        Some(Ok(outbuf))
+        Some(Ok(outbuf.with_meta(*inbuf.meta())))
+    }
+
+    fn buffer_count(&self) -> Option<usize> {
+        self.inner.buffer_count()
+    }
+
+    fn last_buffer_index(&self) -> Option<u32> {
+        self.inner.last_buffer_index()
+    }
+
+    fn queue_state(&self) -> Option<QueueState> {
+        self.inner.queue_state()
+    }
+
+    fn start_instant(&self) -> Option<Instant> {
+        self.inner.start_instant()
+    }
+
+    fn first_frame_latency(&self) -> Option<Duration> {
+        self.inner.first_frame_latency()
+    }
+
+    fn descriptor(&self) -> Option<Descriptor> {
+        self.inner.descriptor().map(|desc| Descriptor {
+            pixfmt: self.pixfmt.clone(),
+            emulated: true,
+            ..desc
+        })
+    }
+
+    fn set_interval(&mut self, interval: Duration) -> Result<()> {
+        self.inner.set_interval(interval)
+    }
+
+    fn set_buffer_count(&mut self, count: usize) -> Result<()> {
+        self.inner.set_buffer_count(count)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.inner.pause()
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.inner.resume()
+    }
+
+    fn is_paused(&self) -> bool {
+        self.inner.is_paused()
+    }
+}
+
+/// A stream scaling frames to a different size
+pub struct ScaledStream<S> {
+    pub inner: S,
+    /// Pipeline resizing the frames
+    pub pipeline: Pipeline,
+}
+
+impl<'a, S> Stream<'a> for ScaledStream<S>
+where
+    S: Stream<'a, Item = Result<Buffer<'a>>>,
+{
+    type Item = Result<Buffer<'a>>;
+
+    fn next(&'a mut self) -> Option<Self::Item> {
+        let inbuf = match self.inner.next()? {
+            Ok(buf) => buf,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let mut bytes = Vec::new();
+        if let Err(e) = self.pipeline.process(&inbuf, &mut bytes) {
+            return Some(Err(e));
+        }
+
+        Some(Ok(Buffer::from(bytes).with_meta(*inbuf.meta())))
+    }
+
+    fn buffer_count(&self) -> Option<usize> {
+        self.inner.buffer_count()
+    }
+
+    fn last_buffer_index(&self) -> Option<u32> {
+        self.inner.last_buffer_index()
+    }
+
+    fn queue_state(&self) -> Option<QueueState> {
+        self.inner.queue_state()
+    }
+
+    fn start_instant(&self) -> Option<Instant> {
+        self.inner.start_instant()
+    }
+
+    fn first_frame_latency(&self) -> Option<Duration> {
+        self.inner.first_frame_latency()
+    }
+
+    fn descriptor(&self) -> Option<Descriptor> {
+        let format = self.pipeline.output_format();
+        self.inner.descriptor().map(|desc| Descriptor {
+            width: format.width,
+            height: format.height,
+            emulated: true,
+            ..desc
+        })
+    }
+
+    fn set_interval(&mut self, interval: Duration) -> Result<()> {
+        self.inner.set_interval(interval)
+    }
+
+    fn set_buffer_count(&mut self, count: usize) -> Result<()> {
+        self.inner.set_buffer_count(count)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.inner.pause()
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.inner.resume()
+    }
+
+    fn is_paused(&self) -> bool {
+        self.inner.is_paused()
     }
 }
@@ -0,0 +1,262 @@
+//! Control caching
+//!
+//! Reading a control usually requires a syscall, which adds up when a UI refreshes dozens of
+//! controls at once. [`ControlCache`] wraps a device and remembers the control descriptors, which
+//! never change, and optionally the current control values for a limited time.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use eye_hal::buffer::Buffer;
+use eye_hal::control;
+use eye_hal::device;
+use eye_hal::error::Result;
+use eye_hal::format::{ImageFormat, PixelFormat};
+use eye_hal::geometry::Rect;
+use eye_hal::platform::Stream as PlatformStream;
+use eye_hal::stream;
+use eye_hal::traits::Device as DeviceTrait;
+
+/// A device wrapper caching control information
+///
+/// Values written through the cache are never served from it, since the driver may adjust them
+/// (e.g. clamp them to the valid range). Use [`ControlCache::invalidate`] when control values
+/// were changed externally, e.g. by another process.
+pub struct ControlCache<D> {
+    inner: D,
+    descriptors: Mutex<Option<Vec<control::Descriptor>>>,
+    values: Mutex<HashMap<u32, (control::State, Instant)>>,
+    ttl: Option<Duration>,
+}
+
+impl<D> ControlCache<D> {
+    /// Returns a cache which only remembers the control descriptors
+    pub fn new(inner: D) -> Self {
+        ControlCache {
+            inner,
+            descriptors: Mutex::new(None),
+            values: Mutex::new(HashMap::new()),
+            ttl: None,
+        }
+    }
+
+    /// Caches control values for the given amount of time
+    pub fn with_value_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Drops all cached control values
+    pub fn invalidate(&self) {
+        self.values.lock().unwrap().clear();
+    }
+
+    /// Returns the wrapped device
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<'a, D> DeviceTrait<'a> for ControlCache<D>
+where
+    D: DeviceTrait<'a>,
+{
+    fn streams(&self) -> Result<Vec<stream::Descriptor>> {
+        self.inner.streams()
+    }
+
+    fn probe_streams(&self, timeout: Duration) -> Result<Vec<stream::Descriptor>> {
+        self.inner.probe_streams(timeout)
+    }
+
+    fn still_streams(&self) -> Result<Vec<stream::Descriptor>> {
+        self.inner.still_streams()
+    }
+
+    fn supports(&self, desc: &stream::Descriptor) -> bool {
+        self.inner.supports(desc)
+    }
+
+    fn formats_at(&self, width: u32, height: u32) -> Result<Vec<PixelFormat>> {
+        self.inner.formats_at(width, height)
+    }
+
+    fn supports_concurrent(&self, descs: &[stream::Descriptor]) -> bool {
+        self.inner.supports_concurrent(descs)
+    }
+
+    fn start_stream(&self, settings: stream::DeviceStreamSettings) -> Result<PlatformStream<'a>> {
+        self.inner.start_stream(settings)
+    }
+
+    fn start_dual_stream(
+        &self,
+        main: stream::DeviceStreamSettings,
+        secondary: stream::DeviceStreamSettings,
+    ) -> Result<(PlatformStream<'a>, PlatformStream<'a>)> {
+        self.inner.start_dual_stream(main, secondary)
+    }
+
+    fn capture_still(
+        &self,
+        pixfmt: PixelFormat,
+        warmup: usize,
+    ) -> Result<(Buffer<'static>, stream::Descriptor)> {
+        self.inner.capture_still(pixfmt, warmup)
+    }
+
+    fn crop_caps(&self) -> Result<device::CropCaps> {
+        self.inner.crop_caps()
+    }
+
+    fn current_format(&self) -> Result<ImageFormat> {
+        self.inner.current_format()
+    }
+
+    fn crop(&self) -> Result<Rect> {
+        self.inner.crop()
+    }
+
+    fn set_crop(&mut self, rect: Rect) -> Result<Rect> {
+        self.inner.set_crop(rect)
+    }
+
+    fn controls(&self) -> Result<Vec<control::Descriptor>> {
+        let mut descriptors = self.descriptors.lock().unwrap();
+        if let Some(descriptors) = &*descriptors {
+            return Ok(descriptors.clone());
+        }
+
+        let controls = self.inner.controls()?;
+        *descriptors = Some(controls.clone());
+        Ok(controls)
+    }
+
+    fn control(&self, id: u32) -> Result<control::State> {
+        let ttl = if let Some(ttl) = self.ttl {
+            ttl
+        } else {
+            return self.inner.control(id);
+        };
+
+        let mut values = self.values.lock().unwrap();
+        if let Some((state, time)) = values.get(&id) {
+            if time.elapsed() < ttl {
+                return Ok(state.clone());
+            }
+        }
+
+        let state = self.inner.control(id)?;
+        values.insert(id, (state.clone(), Instant::now()));
+        Ok(state)
+    }
+
+    fn telemetry(&self) -> Result<Vec<(String, control::State)>> {
+        // telemetry is meant to be polled, so always read the current values
+        self.inner.telemetry()
+    }
+
+    fn control_which(&self, id: u32, which: control::Which) -> Result<control::State> {
+        match which {
+            control::Which::Current => self.control(id),
+            // only current values are cached
+            _ => self.inner.control_which(id, which),
+        }
+    }
+
+    fn control_id(&self, id: control::ControlId) -> Result<u32> {
+        self.inner.control_id(id)
+    }
+
+    fn set_control(&mut self, id: u32, val: &control::State) -> Result<()> {
+        self.values.lock().unwrap().remove(&id);
+        self.inner.set_control(id, val)
+    }
+
+    fn power_line_frequency(&self) -> Result<control::PowerLineFrequency> {
+        self.inner.power_line_frequency()
+    }
+
+    fn set_power_line_frequency(&mut self, freq: control::PowerLineFrequency) -> Result<()> {
+        // we do not know which control backs the frequency
+        self.invalidate();
+        self.inner.set_power_line_frequency(freq)
+    }
+
+    fn set_focus_mode(&mut self, mode: control::FocusMode) -> Result<()> {
+        // switches the auto focus control, which may change the lens position as well
+        self.invalidate();
+        self.inner.set_focus_mode(mode)
+    }
+
+    fn set_focus_absolute(&mut self, value: i64) -> Result<()> {
+        self.invalidate();
+        self.inner.set_focus_absolute(value)
+    }
+
+    fn focus_distance(&self) -> Result<i64> {
+        // the lens moves on its own under automatic focus
+        self.inner.focus_distance()
+    }
+
+    fn set_focus_range(&mut self, range: control::FocusRange) -> Result<()> {
+        self.invalidate();
+        self.inner.set_focus_range(range)
+    }
+
+    fn gain_split_supported(&self) -> bool {
+        self.inner.gain_split_supported()
+    }
+
+    fn set_analog_gain(&mut self, value: f64) -> Result<()> {
+        // we do not know which control backs the gain
+        self.invalidate();
+        self.inner.set_analog_gain(value)
+    }
+
+    fn set_digital_gain(&mut self, value: f64) -> Result<()> {
+        // we do not know which control backs the gain
+        self.invalidate();
+        self.inner.set_digital_gain(value)
+    }
+
+    fn set_metering_roi(&mut self, rect: Rect, mode: control::MeteringMode) -> Result<()> {
+        // uvcvideo exposes the region of interest as regular controls, drop their cached values
+        self.invalidate();
+        self.inner.set_metering_roi(rect, mode)
+    }
+
+    fn lock_auto_values(&mut self) -> Result<()> {
+        self.invalidate();
+        self.inner.lock_auto_values()
+    }
+
+    fn uvc_xu_query(
+        &mut self,
+        unit: u8,
+        selector: u8,
+        query: control::XuQuery,
+        data: &mut [u8],
+    ) -> Result<()> {
+        if query == control::XuQuery::SetCur {
+            // the control may affect others
+            self.invalidate();
+        }
+        self.inner.uvc_xu_query(unit, selector, query, data)
+    }
+
+    fn capabilities(&self) -> Result<device::Capabilities> {
+        self.inner.capabilities()
+    }
+
+    fn audio_inputs(&self) -> Result<Vec<device::AudioInput>> {
+        self.inner.audio_inputs()
+    }
+
+    fn log_status(&self) -> Result<()> {
+        self.inner.log_status()
+    }
+}
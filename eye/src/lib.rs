@@ -14,5 +14,9 @@
 //! conversion (e.g. JPEG -> RGB decoding) by leveraging the `colorconvert` module.
 
 pub mod colorconvert;
+pub mod control;
+pub mod preview;
+pub mod stabilize;
+pub mod stream;
 
 pub use eye_hal as hal;
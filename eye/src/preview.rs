@@ -0,0 +1,267 @@
+//! Downscaled preview streams
+//!
+//! A preview stream receives a downscaled copy of every frame captured by a full resolution
+//! stream, e.g. to render a viewfinder while recording. Only the latest frame is kept, so a slow
+//! consumer never stalls the capture.
+//!
+//! Frames are downscaled in software using nearest neighbour sampling. This works for all
+//! uncompressed packed formats (e.g. RGB, grayscale or YUYV), but not for compressed or planar
+//! formats.
+
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use eye_hal::buffer::Buffer;
+use eye_hal::error::{Error, ErrorKind, Result};
+use eye_hal::format::{ImageFormat, PixelFormat};
+use eye_hal::stream::{Descriptor, QueueState};
+use eye_hal::traits::Stream;
+
+struct Slot {
+    format: Option<ImageFormat>,
+    frame: Option<Buffer<'static>>,
+    closed: bool,
+}
+
+pub(crate) struct Shared {
+    scale: f32,
+    slot: Mutex<Slot>,
+    cond: Condvar,
+}
+
+impl Shared {
+    fn close(&self) {
+        self.slot.lock().unwrap().closed = true;
+        self.cond.notify_all();
+    }
+}
+
+/// A stream of downscaled frames
+///
+/// The stream ends once the full resolution stream it is attached to is dropped.
+pub struct PreviewStream {
+    shared: Arc<Shared>,
+}
+
+impl PreviewStream {
+    pub(crate) fn new(scale: f32) -> Result<Self> {
+        if !(scale > 0.0 && scale <= 1.0) {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "preview scale must be in the range of (0, 1]",
+            ));
+        }
+
+        Ok(PreviewStream {
+            shared: Arc::new(Shared {
+                scale,
+                slot: Mutex::new(Slot {
+                    format: None,
+                    frame: None,
+                    closed: false,
+                }),
+                cond: Condvar::new(),
+            }),
+        })
+    }
+
+    pub(crate) fn shared(&self) -> Arc<Shared> {
+        self.shared.clone()
+    }
+
+    /// Returns the format of the preview frames
+    ///
+    /// The format is only known once the full resolution stream has been started.
+    pub fn format(&self) -> Option<ImageFormat> {
+        self.shared.slot.lock().unwrap().format.clone()
+    }
+}
+
+impl<'a> Stream<'a> for PreviewStream {
+    type Item = Result<Buffer<'a>>;
+
+    fn next(&'a mut self) -> Option<Self::Item> {
+        let mut slot = self.shared.slot.lock().unwrap();
+        loop {
+            if let Some(frame) = slot.frame.take() {
+                return Some(Ok(frame));
+            }
+            if slot.closed {
+                return None;
+            }
+
+            slot = self.shared.cond.wait(slot).unwrap();
+        }
+    }
+}
+
+/// A stream publishing downscaled copies of its frames to preview streams
+pub(crate) struct Source<S> {
+    inner: S,
+    format: ImageFormat,
+    previews: Vec<(Arc<Shared>, ImageFormat)>,
+}
+
+impl<S> Source<S> {
+    pub fn new(inner: S, desc: &Descriptor, previews: Vec<Arc<Shared>>) -> Result<Self> {
+        let unit = if let Some(unit) = unit(&desc.pixfmt) {
+            unit
+        } else {
+            // the previews would otherwise wait for frames forever
+            for preview in &previews {
+                preview.close();
+            }
+            return Err(Error::new(
+                ErrorKind::NotSupported,
+                format!("cannot downscale {} frames", desc.pixfmt),
+            ));
+        };
+
+        let format = ImageFormat::new(desc.width, desc.height, desc.pixfmt.clone());
+        let previews = previews
+            .into_iter()
+            .map(|preview| {
+                let width = (desc.width as f32 * preview.scale).round() as u32;
+                let width = (width - width % unit.1 as u32).max(unit.1 as u32);
+                let height = ((desc.height as f32 * preview.scale).round() as u32).max(1);
+                let format = ImageFormat::new(width, height, desc.pixfmt.clone());
+                preview.slot.lock().unwrap().format = Some(format.clone());
+                (preview, format)
+            })
+            .collect();
+
+        Ok(Source {
+            inner,
+            format,
+            previews,
+        })
+    }
+}
+
+impl<'a, S> Stream<'a> for Source<S>
+where
+    S: Stream<'a, Item = Result<Buffer<'a>>>,
+{
+    type Item = Result<Buffer<'a>>;
+
+    fn next(&'a mut self) -> Option<Self::Item> {
+        let item = self.inner.next()?;
+        if let Ok(buf) = &item {
+            for (preview, format) in &self.previews {
+                // nobody is listening anymore
+                if Arc::strong_count(preview) == 1 {
+                    continue;
+                }
+
+                let mut bytes = Vec::new();
+                if resize(buf.as_bytes(), &self.format, format, &mut bytes) {
+                    let frame = Buffer::from(bytes).with_meta(*buf.meta());
+                    preview.slot.lock().unwrap().frame = Some(frame);
+                    preview.cond.notify_all();
+                }
+            }
+        }
+
+        Some(item)
+    }
+
+    fn buffer_count(&self) -> Option<usize> {
+        self.inner.buffer_count()
+    }
+
+    fn last_buffer_index(&self) -> Option<u32> {
+        self.inner.last_buffer_index()
+    }
+
+    fn queue_state(&self) -> Option<QueueState> {
+        self.inner.queue_state()
+    }
+
+    fn start_instant(&self) -> Option<Instant> {
+        self.inner.start_instant()
+    }
+
+    fn first_frame_latency(&self) -> Option<Duration> {
+        self.inner.first_frame_latency()
+    }
+
+    fn descriptor(&self) -> Option<Descriptor> {
+        self.inner.descriptor()
+    }
+
+    fn set_interval(&mut self, interval: Duration) -> Result<()> {
+        self.inner.set_interval(interval)
+    }
+
+    fn set_buffer_count(&mut self, count: usize) -> Result<()> {
+        self.inner.set_buffer_count(count)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.inner.pause()
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.inner.resume()
+    }
+
+    fn is_paused(&self) -> bool {
+        self.inner.is_paused()
+    }
+}
+
+impl<S> Drop for Source<S> {
+    fn drop(&mut self) {
+        for (preview, _) in &self.previews {
+            preview.close();
+        }
+    }
+}
+
+/// Returns the number of bytes and pixels of the smallest addressable unit of a pixel format
+pub(crate) fn unit(pixfmt: &PixelFormat) -> Option<(usize, usize)> {
+    match pixfmt {
+        // Y0 U Y1 V: two pixels share their chroma samples
+        PixelFormat::Yuyv => Some((4, 2)),
+        PixelFormat::Custom(_)
+        | PixelFormat::Nv12
+        | PixelFormat::I420
+        | PixelFormat::Yv12
+        | PixelFormat::Jpeg => None,
+        _ => match pixfmt.bits() {
+            Some(bits) if bits % 8 == 0 => Some(((bits / 8) as usize, 1)),
+            _ => None,
+        },
+    }
+}
+
+/// Resizes a frame using nearest neighbour sampling
+///
+/// The destination buffer is cleared first, so its allocation can be reused across frames.
+/// Returns `false` if the source buffer is too small.
+pub(crate) fn resize(src: &[u8], from: &ImageFormat, to: &ImageFormat, dst: &mut Vec<u8>) -> bool {
+    let (unit_bytes, unit_pixels) = match unit(&from.pixfmt) {
+        Some(unit) => unit,
+        None => return false,
+    };
+    let src_units = from.width as usize / unit_pixels;
+    let dst_units = to.width as usize / unit_pixels;
+    let src_stride = from.stride.unwrap_or(src_units * unit_bytes);
+    if src.len() < src_stride * from.height as usize {
+        return false;
+    }
+
+    dst.clear();
+    dst.reserve(dst_units * unit_bytes * to.height as usize);
+    for y in 0..to.height as usize {
+        let row = &src[y * from.height as usize / to.height as usize * src_stride..];
+        for x in 0..dst_units {
+            let offset = x * src_units / dst_units * unit_bytes;
+            dst.extend_from_slice(&row[offset..offset + unit_bytes]);
+        }
+    }
+
+    true
+}
@@ -0,0 +1,107 @@
+//! Electronic image stabilization
+//!
+//! Drivers which support moving the crop rectangle while streaming allow for cheap stabilization:
+//! the sensor captures a larger area than the output frames, and the window is shifted to follow
+//! the image content. Estimating the motion (e.g. from a gyroscope or by tracking features) is
+//! up to the application, [`Stabilizer`] turns the motion vectors into crop rectangles.
+
+use eye_hal::error::Result;
+use eye_hal::geometry::Rect;
+use eye_hal::traits::Device;
+
+/// Tracks the crop window for electronic image stabilization
+///
+/// The window starts at its neutral position. Motion of the image content is compensated by
+/// moving the window along with it, as far as the sensor bounds permit. Compensations accumulate,
+/// so intentional camera movement (e.g. panning) is compensated as well until the window hits the
+/// bounds. Use [`Stabilizer::with_recentering`] to let the window drift back to its neutral
+/// position over time.
+///
+/// # Example
+///
+/// ```no_run
+/// # use eye::hal::{platform::Device, traits::Device as _, Result};
+/// # fn motion() -> (i32, i32) { (0, 0) }
+/// # fn example(dev: &mut Device) -> Result<()> {
+/// use eye::hal::geometry::Rect;
+/// use eye::stabilize::Stabilizer;
+///
+/// // capture a 1280x720 window of the full sensor area, leaving a margin for the compensation
+/// let bounds = dev.crop_caps()?.bounds;
+/// let window = Rect::new(
+///     bounds.left + (bounds.width as i32 - 1280) / 2,
+///     bounds.top + (bounds.height as i32 - 720) / 2,
+///     1280,
+///     720,
+/// );
+/// let mut stabilizer = Stabilizer::new(bounds, window);
+///
+/// // for every frame
+/// let (dx, dy) = motion();
+/// stabilizer.apply(dev, dx, dy)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Stabilizer {
+    bounds: Rect,
+    neutral: Rect,
+    window: Rect,
+    recentering: f32,
+}
+
+impl Stabilizer {
+    /// Creates a new stabilizer moving `window` within `bounds`
+    ///
+    /// The bounds are usually the [`CropCaps::bounds`](eye_hal::device::CropCaps::bounds) of
+    /// the device.
+    pub fn new(bounds: Rect, window: Rect) -> Self {
+        let neutral = window.clamp_to(&bounds);
+        Stabilizer {
+            bounds,
+            neutral,
+            window: neutral,
+            recentering: 0.0,
+        }
+    }
+
+    /// Moves the window back to its neutral position by a fraction of the distance per update
+    ///
+    /// # Arguments
+    ///
+    /// * `factor` - Fraction in the range of [0, 1], 0 disables recentering
+    pub fn with_recentering(mut self, factor: f32) -> Self {
+        self.recentering = factor.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Returns the current crop window
+    pub fn window(&self) -> Rect {
+        self.window
+    }
+
+    /// Compensates a motion of the image content by (dx, dy) pixels, returns the new window
+    pub fn update(&mut self, dx: i32, dy: i32) -> Rect {
+        let back =
+            |pos: i32, neutral: i32| ((neutral - pos) as f32 * self.recentering).round() as i32;
+        let dx = dx.saturating_add(back(self.window.left, self.neutral.left));
+        let dy = dy.saturating_add(back(self.window.top, self.neutral.top));
+
+        self.window = self.window.offset(dx, dy).clamp_to(&self.bounds);
+        self.window
+    }
+
+    /// Moves the window back to its neutral position
+    pub fn reset(&mut self) {
+        self.window = self.neutral;
+    }
+
+    /// Compensates a motion and moves the crop rectangle of the device accordingly
+    ///
+    /// Returns the rectangle chosen by the driver, which may differ from the window if it does
+    /// not meet the driver constraints (e.g. alignment).
+    pub fn apply<'a, D: Device<'a>>(&mut self, dev: &mut D, dx: i32, dy: i32) -> Result<Rect> {
+        let window = self.update(dx, dy);
+        dev.set_crop(window)
+    }
+}
@@ -0,0 +1,174 @@
+use std::mem;
+use std::time::{Duration, Instant};
+
+use eye_hal::buffer::Buffer;
+use eye_hal::error::Result;
+use eye_hal::stream::{Descriptor, QueueState};
+use eye_hal::traits::Stream;
+
+/// Only every n-th pixel of every n-th row is compared
+const SAMPLE_STEP: usize = 8;
+
+/// A stream skipping frames which barely differ from the previous one
+///
+/// Static scenes (e.g. of surveillance cameras) produce long runs of nearly identical frames.
+/// This adapter compares the luma plane of every frame to the last frame it passed on and drops
+/// the frame if the mean absolute difference is below a threshold. Only a sparse grid of pixels
+/// is compared, which is cheap enough to keep up with the full frame rate.
+///
+/// Passed on frames keep their sequence numbers, so skipped frames show up as gaps in the
+/// sequence. The metadata is not changed otherwise, so frames of streams without sequence
+/// numbers (e.g. UVC streams) carry no hint that frames were skipped before them. Compare
+/// [`Dedupe::skipped`] before and after reading a frame in that case. Frames of formats without
+/// a luma plane (e.g. compressed ones) are never skipped.
+/// Since the inner stream reuses its buffers, the passed on frames are copied.
+///
+/// # Example
+///
+/// ```no_run
+/// # use eye::hal::{platform, traits::Stream};
+/// # fn example(stream: platform::Stream) {
+/// use eye::stream::Dedupe;
+///
+/// // ignore sensor noise and flickering
+/// let mut stream = Dedupe::new(stream, 3.0);
+/// while let Some(Ok(frame)) = stream.next() {
+///     println!("frame {:?} changed", frame.meta().sequence);
+/// }
+/// # }
+/// ```
+pub struct Dedupe<S> {
+    inner: S,
+    threshold: f32,
+    /// Sampled luma of the last frame which was passed on
+    reference: Vec<u8>,
+    /// Sampled luma of the current frame
+    samples: Vec<u8>,
+    /// Copy of the frame which was passed on
+    frame: Vec<u8>,
+    skipped: u64,
+}
+
+impl<S> Dedupe<S> {
+    /// Creates a new adapter
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - Mean absolute difference of the luma samples in the range of [0, 255],
+    ///   frames with a smaller difference are skipped
+    pub fn new(inner: S, threshold: f32) -> Self {
+        Dedupe {
+            inner,
+            threshold,
+            reference: Vec::new(),
+            samples: Vec::new(),
+            frame: Vec::new(),
+            skipped: 0,
+        }
+    }
+
+    /// Returns the number of frames which were skipped so far
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Returns the wrapped stream
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+/// Collects the luma samples of an image
+fn sample(luma: &[u8], desc: &Descriptor, out: &mut Vec<u8>) {
+    let width = desc.width as usize;
+    out.clear();
+    for y in (SAMPLE_STEP / 2..desc.height as usize).step_by(SAMPLE_STEP) {
+        if let Some(row) = luma.get(y * width..(y + 1) * width) {
+            out.extend(row.iter().skip(SAMPLE_STEP / 2).step_by(SAMPLE_STEP));
+        }
+    }
+}
+
+/// Returns the mean absolute difference of two sample sets of equal length
+fn difference(a: &[u8], b: &[u8]) -> f32 {
+    let sum: u64 = a.iter().zip(b).map(|(a, b)| a.abs_diff(*b) as u64).sum();
+    sum as f32 / a.len().max(1) as f32
+}
+
+impl<'a, S> Stream<'a> for Dedupe<S>
+where
+    S: for<'b> Stream<'b, Item = Result<Buffer<'b>>>,
+{
+    type Item = Result<Buffer<'a>>;
+
+    fn next(&'a mut self) -> Option<Self::Item> {
+        let meta = loop {
+            let desc = self.inner.descriptor();
+            let buf = match self.inner.next()? {
+                Ok(buf) => buf,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let luma = desc.as_ref().map(|desc| (desc, buf.luma(desc)));
+            if let Some((desc, Ok(luma))) = luma {
+                sample(&luma, desc, &mut self.samples);
+                let comparable =
+                    !self.samples.is_empty() && self.samples.len() == self.reference.len();
+                if comparable && difference(&self.samples, &self.reference) < self.threshold {
+                    self.skipped += 1;
+                    continue;
+                }
+                mem::swap(&mut self.samples, &mut self.reference);
+            }
+
+            self.frame.clear();
+            self.frame.extend_from_slice(buf.as_bytes());
+            break *buf.meta();
+        };
+
+        Some(Ok(Buffer::from(&self.frame[..]).with_meta(meta)))
+    }
+
+    fn buffer_count(&self) -> Option<usize> {
+        self.inner.buffer_count()
+    }
+
+    fn queue_state(&self) -> Option<QueueState> {
+        self.inner.queue_state()
+    }
+
+    fn start_instant(&self) -> Option<Instant> {
+        self.inner.start_instant()
+    }
+
+    fn first_frame_latency(&self) -> Option<Duration> {
+        self.inner.first_frame_latency()
+    }
+
+    fn descriptor(&self) -> Option<Descriptor> {
+        self.inner.descriptor()
+    }
+
+    fn set_interval(&mut self, interval: Duration) -> Result<()> {
+        self.inner.set_interval(interval)
+    }
+
+    fn set_buffer_count(&mut self, count: usize) -> Result<()> {
+        self.inner.set_buffer_count(count)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.inner.pause()?;
+        // the scene may have changed by the time the stream is resumed
+        self.reference.clear();
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.inner.resume()
+    }
+
+    fn is_paused(&self) -> bool {
+        self.inner.is_paused()
+    }
+}
@@ -0,0 +1,277 @@
+use std::time::{Duration, Instant};
+
+use eye_hal::buffer::{Buffer, Metadata};
+use eye_hal::error::{Error, ErrorKind, Result};
+use eye_hal::stream::{Descriptor, FieldOrder, QueueState};
+use eye_hal::traits::Stream;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Deinterlacing method
+pub enum Method {
+    /// Line doubles a single field
+    ///
+    /// Avoids combing artifacts, but halves the vertical resolution.
+    Bob,
+    /// Interleaves the lines of both fields
+    ///
+    /// Keeps the full vertical resolution, but moving objects show combing artifacts.
+    Weave,
+}
+
+/// A stream deinterlacing its frames
+///
+/// The field order of each buffer is taken from its metadata, so the adapter does the right thing
+/// for whichever field order the driver negotiated. Buffers which contain both fields are
+/// converted into a single frame, using the field which was captured first for [`Method::Bob`].
+/// Buffers of alternating streams contain a single field, each of them is turned into a full
+/// frame by combining it with the previous field for [`Method::Weave`].
+///
+/// Progressive frames and frames without field information are passed through unchanged. Only
+/// packed formats (e.g. RGB, grayscale or YUYV) are supported.
+///
+/// # Example
+///
+/// ```no_run
+/// # use eye::hal::{platform, stream::Descriptor, traits::Stream, Result};
+/// # fn example(stream: platform::Stream, desc: &Descriptor) -> Result<()> {
+/// use eye::stream::{deinterlace, Deinterlace};
+///
+/// let mut stream = Deinterlace::new(stream, desc, deinterlace::Method::Bob)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Deinterlace<S> {
+    inner: S,
+    method: Method,
+    /// Bytes per line
+    line: usize,
+    /// Lines per frame
+    height: usize,
+    out: Vec<u8>,
+    /// Last field of an alternating stream and whether it was the top field
+    prev: Option<(Vec<u8>, bool)>,
+}
+
+impl<S> Deinterlace<S> {
+    /// Creates a new adapter for a stream with the given format
+    pub fn new(inner: S, desc: &Descriptor, method: Method) -> Result<Self> {
+        let bits = match desc.pixfmt.bits() {
+            Some(bits)
+                if bits % 8 == 0 && !desc.pixfmt.is_planar() && !desc.pixfmt.is_compressed() =>
+            {
+                bits
+            }
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::NotSupported,
+                    format!("cannot deinterlace {} frames", desc.pixfmt),
+                ))
+            }
+        };
+
+        Ok(Deinterlace {
+            inner,
+            method,
+            line: desc.width as usize * (bits / 8) as usize,
+            height: desc.height as usize,
+            out: Vec::new(),
+            prev: None,
+        })
+    }
+
+    /// Returns the wrapped stream
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<'a, S> Stream<'a> for Deinterlace<S>
+where
+    S: Stream<'a, Item = Result<Buffer<'a>>>,
+{
+    type Item = Result<Buffer<'a>>;
+
+    fn next(&'a mut self) -> Option<Self::Item> {
+        let buf = match self.inner.next()? {
+            Ok(buf) => buf,
+            Err(e) => return Some(Err(e)),
+        };
+        let field = match buf.meta().field {
+            Some(FieldOrder::Progressive) | None => return Some(Ok(buf)),
+            Some(field) => field,
+        };
+
+        let frame = Frame {
+            line: self.line,
+            height: self.height,
+        };
+        let data = buf.as_bytes();
+        let single = matches!(field, FieldOrder::Top | FieldOrder::Bottom);
+        let needed = if single {
+            frame.field_lines(field == FieldOrder::Top) * frame.line
+        } else {
+            frame.height * frame.line
+        };
+        if data.len() < needed {
+            return Some(Err(Error::new(
+                ErrorKind::Other,
+                "buffer is too small for the frame format",
+            )));
+        }
+
+        self.out.clear();
+        match (field, self.method) {
+            // The fields are interleaved already.
+            (
+                FieldOrder::Interlaced
+                | FieldOrder::InterlacedTopFirst
+                | FieldOrder::InterlacedBottomFirst,
+                Method::Weave,
+            ) => return Some(Ok(buf)),
+            (FieldOrder::Top | FieldOrder::Bottom, method) => {
+                let top = field == FieldOrder::Top;
+                let prev = self.prev.take();
+                match &prev {
+                    Some((prev, prev_top)) if method == Method::Weave && *prev_top != top => {
+                        let (top_data, bottom_data) = if top {
+                            (data, &prev[..])
+                        } else {
+                            (&prev[..], data)
+                        };
+                        frame.weave(
+                            &mut self.out,
+                            (top_data, 0, frame.line),
+                            (bottom_data, 0, frame.line),
+                        );
+                    }
+                    _ => frame.bob(&mut self.out, (data, 0, frame.line), top),
+                }
+
+                if method == Method::Weave {
+                    // The inner stream reuses its buffer, so keep a copy of the field.
+                    let mut prev = prev.map(|(prev, _)| prev).unwrap_or_default();
+                    prev.clear();
+                    prev.extend_from_slice(&data[..needed]);
+                    self.prev = Some((prev, top));
+                }
+            }
+            (field, Method::Bob) => {
+                // Line double the field which was captured first.
+                let top = !matches!(
+                    field,
+                    FieldOrder::InterlacedBottomFirst | FieldOrder::SequentialBottomTop
+                );
+                frame.bob(&mut self.out, frame.lines(data, field, top), top);
+            }
+            (field, Method::Weave) => frame.weave(
+                &mut self.out,
+                frame.lines(data, field, true),
+                frame.lines(data, field, false),
+            ),
+        }
+
+        let meta = Metadata {
+            field: Some(FieldOrder::Progressive),
+            ..*buf.meta()
+        };
+        Some(Ok(Buffer::from(&self.out[..]).with_meta(meta)))
+    }
+
+    fn buffer_count(&self) -> Option<usize> {
+        self.inner.buffer_count()
+    }
+
+    fn queue_state(&self) -> Option<QueueState> {
+        self.inner.queue_state()
+    }
+
+    fn start_instant(&self) -> Option<Instant> {
+        self.inner.start_instant()
+    }
+
+    fn first_frame_latency(&self) -> Option<Duration> {
+        self.inner.first_frame_latency()
+    }
+
+    fn descriptor(&self) -> Option<Descriptor> {
+        self.inner.descriptor()
+    }
+
+    fn set_interval(&mut self, interval: Duration) -> Result<()> {
+        self.inner.set_interval(interval)
+    }
+
+    fn set_buffer_count(&mut self, count: usize) -> Result<()> {
+        self.inner.set_buffer_count(count)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.inner.pause()?;
+        // the next field is not related to the last one
+        self.prev = None;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.inner.resume()
+    }
+
+    fn is_paused(&self) -> bool {
+        self.inner.is_paused()
+    }
+}
+
+/// Lines of a field as (data, offset of the first line, distance between lines) in bytes
+type Lines<'a> = (&'a [u8], usize, usize);
+
+struct Frame {
+    line: usize,
+    height: usize,
+}
+
+impl Frame {
+    /// Returns the number of lines of a field, the top field has the extra line of odd heights
+    fn field_lines(&self, top: bool) -> usize {
+        if top {
+            self.height.div_ceil(2)
+        } else {
+            self.height / 2
+        }
+    }
+
+    /// Locates a field in a buffer containing both fields
+    fn lines<'a>(&self, data: &'a [u8], field: FieldOrder, top: bool) -> Lines<'a> {
+        match field {
+            FieldOrder::SequentialTopBottom if top => (data, 0, self.line),
+            FieldOrder::SequentialTopBottom => {
+                (data, self.field_lines(true) * self.line, self.line)
+            }
+            FieldOrder::SequentialBottomTop if top => {
+                (data, self.field_lines(false) * self.line, self.line)
+            }
+            FieldOrder::SequentialBottomTop => (data, 0, self.line),
+            _ if top => (data, 0, 2 * self.line),
+            _ => (data, self.line, 2 * self.line),
+        }
+    }
+
+    fn field_line<'a>(&self, (data, offset, step): Lines<'a>, index: usize) -> &'a [u8] {
+        let start = offset + index * step;
+        &data[start..start + self.line]
+    }
+
+    fn bob(&self, out: &mut Vec<u8>, field: Lines, top: bool) {
+        let lines = self.field_lines(top);
+        for y in 0..self.height {
+            let index = (y / 2).min(lines.saturating_sub(1));
+            out.extend_from_slice(self.field_line(field, index));
+        }
+    }
+
+    fn weave(&self, out: &mut Vec<u8>, top: Lines, bottom: Lines) {
+        for y in 0..self.height {
+            let field = if y % 2 == 0 { top } else { bottom };
+            out.extend_from_slice(self.field_line(field, y / 2));
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use eye_hal::buffer::Buffer;
+use eye_hal::error::Result;
+use eye_hal::stream::{Descriptor, QueueState};
+use eye_hal::traits::Stream;
+
+/// A stream measuring the frame rate it actually delivers
+///
+/// The negotiated frame interval is only an upper bound, devices often deliver fewer frames
+/// (e.g. because of USB bandwidth limits or long exposure times). The rate is computed from the
+/// timestamps of the most recent frames. Frames without a timestamp are timed on arrival.
+///
+/// # Example
+///
+/// ```no_run
+/// # use eye::hal::{platform, traits::Stream};
+/// # fn example(stream: platform::Stream) {
+/// use eye::stream::FpsMeter;
+///
+/// let mut stream = FpsMeter::new(stream, 30);
+/// let requested = stream.descriptor().map(|desc| desc.fps());
+/// while let Some(Ok(_buf)) = stream.next() {}
+/// println!("requested {:?} fps, got {:?} fps", requested, stream.measured_fps());
+/// # }
+/// ```
+pub struct FpsMeter<S> {
+    inner: S,
+    window: usize,
+    /// Time at which the meter was created, the base of arrival timestamps
+    start: Instant,
+    timestamps: VecDeque<Duration>,
+}
+
+impl<S> FpsMeter<S> {
+    /// Creates a new meter averaging over the last `window` frames
+    pub fn new(inner: S, window: usize) -> Self {
+        // At least two timestamps are needed to measure anything.
+        let window = window.max(2);
+        FpsMeter {
+            inner,
+            window,
+            start: Instant::now(),
+            timestamps: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Returns the average frame rate of the last frames
+    ///
+    /// Returns `None` until at least two frames have been received.
+    pub fn measured_fps(&self) -> Option<f64> {
+        let first = self.timestamps.front()?;
+        let last = self.timestamps.back()?;
+        let elapsed = last.checked_sub(*first)?.as_secs_f64();
+        if elapsed <= 0.0 {
+            return None;
+        }
+
+        Some((self.timestamps.len() - 1) as f64 / elapsed)
+    }
+
+    /// Forgets all measurements, e.g. after changing the frame interval
+    pub fn reset(&mut self) {
+        self.timestamps.clear();
+    }
+
+    /// Returns the wrapped stream
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<'a, S> Stream<'a> for FpsMeter<S>
+where
+    S: Stream<'a, Item = Result<Buffer<'a>>>,
+{
+    type Item = Result<Buffer<'a>>;
+
+    fn next(&'a mut self) -> Option<Self::Item> {
+        let start = self.start;
+        let item = self.inner.next()?;
+        if let Ok(buf) = &item {
+            let timestamp = buf.meta().timestamp.unwrap_or_else(|| start.elapsed());
+            if self.timestamps.len() == self.window {
+                self.timestamps.pop_front();
+            }
+            self.timestamps.push_back(timestamp);
+        }
+
+        Some(item)
+    }
+
+    fn buffer_count(&self) -> Option<usize> {
+        self.inner.buffer_count()
+    }
+
+    fn last_buffer_index(&self) -> Option<u32> {
+        self.inner.last_buffer_index()
+    }
+
+    fn queue_state(&self) -> Option<QueueState> {
+        self.inner.queue_state()
+    }
+
+    fn start_instant(&self) -> Option<Instant> {
+        self.inner.start_instant()
+    }
+
+    fn first_frame_latency(&self) -> Option<Duration> {
+        self.inner.first_frame_latency()
+    }
+
+    fn descriptor(&self) -> Option<Descriptor> {
+        self.inner.descriptor()
+    }
+
+    fn set_interval(&mut self, interval: Duration) -> Result<()> {
+        self.inner.set_interval(interval)?;
+        self.reset();
+        Ok(())
+    }
+
+    fn set_buffer_count(&mut self, count: usize) -> Result<()> {
+        self.inner.set_buffer_count(count)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.inner.pause()
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.inner.resume()?;
+        // the pause would show up as a slow frame
+        self.reset();
+        Ok(())
+    }
+
+    fn is_paused(&self) -> bool {
+        self.inner.is_paused()
+    }
+}
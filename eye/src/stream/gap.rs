@@ -0,0 +1,189 @@
+use std::time::{Duration, Instant};
+
+use eye_hal::buffer::{Buffer, Metadata};
+use eye_hal::error::Result;
+use eye_hal::stream::{Descriptor, QueueState};
+use eye_hal::traits::Stream;
+
+/// Gaps larger than this are treated as a restart of the sequence counter rather than dropped
+/// frames, e.g. when the driver resets the counter after the stream was stopped.
+const MAX_GAP: u32 = 1 << 31;
+
+/// A stream filling sequence gaps with synthesized frames
+///
+/// Drivers drop frames when the application does not dequeue buffers fast enough. The dropped
+/// frames show up as gaps in the buffer sequence numbers. For each missing frame, this adapter
+/// calls a user provided function with the last frame that was received and the distance to it
+/// in sequence numbers (starting at 1). The returned buffer is emitted in place of the missing
+/// frame, so consumers see a continuous sequence.
+///
+/// Since the inner stream reuses its buffers, the adapter keeps a copy of the last frame around.
+/// Buffers without a sequence number are passed through unchanged.
+///
+/// # Example
+///
+/// ```no_run
+/// # use eye::hal::{buffer::Buffer, traits::Stream, Result};
+/// # fn example<'a>(stream: impl Stream<'a, Item = Result<Buffer<'a>>>) {
+/// use eye::stream::GapFill;
+///
+/// // repeat the last frame for every dropped one
+/// let stream = GapFill::duplicate(stream);
+/// # }
+/// ```
+pub struct GapFill<S, F> {
+    inner: S,
+    fill: F,
+    state: State,
+}
+
+struct State {
+    /// Copy of the last frame that was received
+    last: Vec<u8>,
+    last_meta: Metadata,
+    last_sequence: Option<u32>,
+    /// Frame received after a gap, emitted once the gap is filled
+    pending: Option<Buffer<'static>>,
+    /// Number of frames that still need to be synthesized
+    missing: u32,
+    /// Distance of the next synthesized frame to the last received one
+    distance: u32,
+}
+
+impl<S, F> GapFill<S, F>
+where
+    F: FnMut(&Buffer, u32) -> Buffer<'static>,
+{
+    /// Creates a new adapter calling `fill` for every missing frame
+    pub fn new(inner: S, fill: F) -> Self {
+        GapFill {
+            inner,
+            fill,
+            state: State {
+                last: Vec::new(),
+                last_meta: Metadata::default(),
+                last_sequence: None,
+                pending: None,
+                missing: 0,
+                distance: 0,
+            },
+        }
+    }
+}
+
+impl<S> GapFill<S, fn(&Buffer, u32) -> Buffer<'static>> {
+    /// Creates a new adapter which repeats the last frame for every missing one
+    pub fn duplicate(inner: S) -> Self {
+        GapFill::new(inner, |prev, _| prev.clone().own())
+    }
+}
+
+impl State {
+    fn remember(&mut self, buf: &Buffer) {
+        self.last.clear();
+        self.last.extend_from_slice(buf.as_bytes());
+        self.last_meta = *buf.meta();
+        self.last_sequence = buf.meta().sequence;
+    }
+
+    fn synthesize<F>(&mut self, fill: &mut F) -> Buffer<'static>
+    where
+        F: FnMut(&Buffer, u32) -> Buffer<'static>,
+    {
+        let prev = Buffer::from(&self.last[..]).with_meta(self.last_meta);
+        let buf = fill(&prev, self.distance);
+        let sequence = self
+            .last_sequence
+            .map(|sequence| sequence.wrapping_add(self.distance));
+        let meta = Metadata {
+            sequence,
+            ..*buf.meta()
+        };
+
+        self.missing -= 1;
+        self.distance += 1;
+        buf.with_meta(meta)
+    }
+}
+
+impl<'a, S, F> Stream<'a> for GapFill<S, F>
+where
+    S: Stream<'a, Item = Result<Buffer<'a>>>,
+    F: FnMut(&Buffer, u32) -> Buffer<'static>,
+{
+    type Item = Result<Buffer<'a>>;
+
+    fn next(&'a mut self) -> Option<Self::Item> {
+        if self.state.missing > 0 {
+            return Some(Ok(self.state.synthesize(&mut self.fill)));
+        }
+        if let Some(buf) = self.state.pending.take() {
+            self.state.remember(&buf);
+            return Some(Ok(buf));
+        }
+
+        let buf = match self.inner.next()? {
+            Ok(buf) => buf,
+            Err(e) => return Some(Err(e)),
+        };
+
+        if let (Some(prev), Some(sequence)) = (self.state.last_sequence, buf.meta().sequence) {
+            // wrapping arithmetic keeps this correct when the counter overflows
+            let gap = sequence.wrapping_sub(prev).wrapping_sub(1);
+            if gap > 0 && gap < MAX_GAP {
+                // The inner stream reuses its buffer once we return, so keep a copy until all
+                // missing frames have been emitted.
+                self.state.pending = Some(buf.own());
+                self.state.missing = gap;
+                self.state.distance = 1;
+                return Some(Ok(self.state.synthesize(&mut self.fill)));
+            }
+        }
+
+        self.state.remember(&buf);
+        Some(Ok(buf))
+    }
+
+    fn buffer_count(&self) -> Option<usize> {
+        self.inner.buffer_count()
+    }
+
+    fn queue_state(&self) -> Option<QueueState> {
+        self.inner.queue_state()
+    }
+
+    fn start_instant(&self) -> Option<Instant> {
+        self.inner.start_instant()
+    }
+
+    fn first_frame_latency(&self) -> Option<Duration> {
+        self.inner.first_frame_latency()
+    }
+
+    fn descriptor(&self) -> Option<Descriptor> {
+        self.inner.descriptor()
+    }
+
+    fn set_interval(&mut self, interval: Duration) -> Result<()> {
+        self.inner.set_interval(interval)
+    }
+
+    fn set_buffer_count(&mut self, count: usize) -> Result<()> {
+        self.inner.set_buffer_count(count)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.inner.pause()?;
+        // drivers may restart the sequence counter
+        self.state.last_sequence = None;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.inner.resume()
+    }
+
+    fn is_paused(&self) -> bool {
+        self.inner.is_paused()
+    }
+}
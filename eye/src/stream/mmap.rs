@@ -0,0 +1,170 @@
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+use memmap2::{MmapMut, MmapOptions};
+
+use eye_hal::buffer::Buffer;
+use eye_hal::error::{Error, ErrorKind, Result};
+use eye_hal::traits::Stream;
+
+/// The file is grown in steps of this size to keep the number of remap operations low
+const GROWTH: u64 = 64 << 20;
+
+/// A file sink writing frames into a memory mapped region
+///
+/// Frames are copied straight from the stream buffers into the page cache, so there is no
+/// intermediate userspace buffer as with `write()`. The file grows on demand up to a maximum
+/// size and is truncated to the number of bytes written once the sink is finished (or dropped).
+///
+/// # Example
+///
+/// ```no_run
+/// # use eye::hal::{platform, traits::Stream, Result};
+/// # fn example(mut stream: platform::Stream) -> Result<()> {
+/// use eye::stream::MmapSink;
+///
+/// let mut sink = MmapSink::create("capture.yuyv", 16 << 30)?;
+/// while let Some(frame) = stream.next() {
+///     let frame = frame?;
+///     if frame.as_bytes().len() as u64 > sink.remaining() {
+///         break;
+///     }
+///     sink.write(frame.as_bytes())?;
+/// }
+/// sink.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct MmapSink {
+    file: File,
+    map: Option<MmapMut>,
+    /// Number of bytes written
+    pos: u64,
+    /// Current length of the file
+    len: u64,
+    max_size: u64,
+    finished: bool,
+}
+
+impl MmapSink {
+    /// Creates (or truncates) a file which may grow up to `max_size` bytes
+    pub fn create<P: AsRef<Path>>(path: P, max_size: u64) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        Ok(MmapSink {
+            file,
+            map: None,
+            pos: 0,
+            len: 0,
+            max_size,
+            finished: false,
+        })
+    }
+
+    /// Returns the number of bytes written so far
+    pub fn written(&self) -> u64 {
+        self.pos
+    }
+
+    /// Returns the number of bytes which can still be written
+    pub fn remaining(&self) -> u64 {
+        self.max_size - self.pos
+    }
+
+    /// Appends data (e.g. the bytes of a frame) to the file
+    ///
+    /// Fails without writing anything if the data does not fit within the maximum size.
+    pub fn write(&mut self, data: &[u8]) -> Result<()> {
+        let end = self.pos + data.len() as u64;
+        if end > self.max_size {
+            return Err(Error::new(ErrorKind::Other, "file size limit reached"));
+        }
+        if end > self.len {
+            self.grow(end)?;
+        }
+
+        if let Some(map) = &mut self.map {
+            map[self.pos as usize..end as usize].copy_from_slice(data);
+        }
+        self.pos = end;
+        Ok(())
+    }
+
+    /// Flushes the written data and truncates the file to its final size
+    ///
+    /// Returns the number of bytes written.
+    pub fn finish(mut self) -> Result<u64> {
+        self.close()
+    }
+
+    /// Extends the file and the mapping so at least `size` bytes fit
+    fn grow(&mut self, size: u64) -> Result<()> {
+        let len = size.max(self.len + GROWTH).min(self.max_size);
+        // Unmapping does not discard the data written so far, the kernel writes the dirty pages
+        // back on its own.
+        self.map = None;
+        self.file.set_len(len)?;
+        let map = unsafe { MmapOptions::new().len(len as usize).map_mut(&self.file)? };
+        self.map = Some(map);
+        self.len = len;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<u64> {
+        if self.finished {
+            return Ok(self.pos);
+        }
+        self.finished = true;
+
+        if let Some(map) = self.map.take() {
+            map.flush()?;
+        }
+        // The file was grown ahead of time, so cut off the unused tail. This must happen after
+        // unmapping, accessing pages beyond the end of the file is a fault.
+        self.file.set_len(self.pos)?;
+        self.file.sync_data()?;
+        Ok(self.pos)
+    }
+}
+
+impl Drop for MmapSink {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+/// Writes the frames of a stream into a memory mapped file, see [`MmapSink`]
+///
+/// Frames are written back-to-back until the stream ends (e.g. because it was paused) or the next
+/// frame does not fit within `max_size`. The file is flushed in either case, as well as when the
+/// stream fails. Returns the number of bytes written.
+pub fn pipe_to_mmap_file<S, P>(stream: &mut S, path: P, max_size: u64) -> Result<u64>
+where
+    S: for<'a> Stream<'a, Item = Result<Buffer<'a>>>,
+    P: AsRef<Path>,
+{
+    let mut sink = MmapSink::create(path, max_size)?;
+
+    while let Some(frame) = stream.next() {
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(e) => {
+                sink.close()?;
+                return Err(e);
+            }
+        };
+
+        let data = frame.as_bytes();
+        if data.len() as u64 > sink.remaining() {
+            break;
+        }
+        sink.write(data)?;
+    }
+
+    sink.finish()
+}
@@ -0,0 +1,22 @@
+//! Stream adapters
+//!
+//! Adapters wrap another stream and transform the buffers it produces. They implement the
+//! [`Stream`](eye_hal::traits::Stream) trait themselves, so they can be stacked.
+
+mod dedupe;
+pub mod deinterlace;
+mod fps;
+mod gap;
+mod mmap;
+mod reader;
+pub mod sync;
+mod tagged;
+
+pub use dedupe::Dedupe;
+pub use deinterlace::Deinterlace;
+pub use fps::FpsMeter;
+pub use gap::GapFill;
+pub use mmap::{pipe_to_mmap_file, MmapSink};
+pub use reader::Reader;
+pub use sync::SyncGroup;
+pub use tagged::TaggedStream;
@@ -0,0 +1,74 @@
+use std::io;
+
+use eye_hal::buffer::Buffer;
+use eye_hal::error::Result;
+use eye_hal::traits::Stream;
+
+/// A reader serving the frames of a stream back-to-back
+///
+/// Frames are read from the stream on demand and their bytes concatenated without any framing,
+/// which is what tools consuming raw video (e.g. ffmpeg with `-f rawvideo`) expect on their
+/// standard input. Since the stream reuses its buffers, the current frame is copied before it is
+/// served. The reader reaches its end once the stream does, stream errors are reported as I/O
+/// errors.
+///
+/// # Example
+///
+/// ```no_run
+/// # use eye::hal::platform;
+/// # fn example(stream: platform::Stream, stdin: &mut std::process::ChildStdin) {
+/// use eye::stream::Reader;
+///
+/// let mut reader = Reader::new(stream);
+/// std::io::copy(&mut reader, stdin).unwrap();
+/// # }
+/// ```
+pub struct Reader<S> {
+    inner: S,
+    frame: Vec<u8>,
+    /// Number of bytes of the current frame which were served already
+    pos: usize,
+}
+
+impl<S> Reader<S> {
+    /// Creates a new reader for the frames of `inner`
+    pub fn new(inner: S) -> Self {
+        Reader {
+            inner,
+            frame: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Returns the wrapped stream
+    ///
+    /// The unread bytes of the current frame are lost.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> io::Read for Reader<S>
+where
+    S: for<'a> Stream<'a, Item = Result<Buffer<'a>>>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.frame.len() {
+            let frame = match self.inner.next() {
+                Some(Ok(frame)) => frame,
+                Some(Err(e)) => return Err(io::Error::other(e)),
+                // end of stream
+                None => return Ok(0),
+            };
+
+            self.frame.clear();
+            self.frame.extend_from_slice(frame.as_bytes());
+            self.pos = 0;
+        }
+
+        let len = buf.len().min(self.frame.len() - self.pos);
+        buf[..len].copy_from_slice(&self.frame[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use eye_hal::buffer::Buffer;
+use eye_hal::error::{Error, ErrorKind, Result};
+use eye_hal::traits::Stream;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// How frames of the streams are matched
+pub enum Pairing {
+    /// Frames with the same sequence number belong together
+    ///
+    /// Only use this for streams which are driven by the same trigger (e.g. hardware synced
+    /// cameras or the streams of a dual stream device), whose sequence counters run in lockstep.
+    Sequence,
+    /// Frames whose capture times differ by at most the given tolerance belong together
+    ///
+    /// Both streams must use the same capture clock, which is the case unless configured
+    /// otherwise.
+    Timestamp(Duration),
+}
+
+/// Two streams whose frames are paired up
+///
+/// Frames which have no partner in the other stream (e.g. because one camera dropped a frame) are
+/// discarded, so every item holds a frame of each stream which were captured at the same
+/// instant. Since the streams reuse their buffers, the frames are copied.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use eye::hal::{platform, traits::Stream};
+/// # fn example(left: platform::Stream, right: platform::Stream) {
+/// use eye::stream::{sync::Pairing, SyncGroup};
+///
+/// let mut stereo = SyncGroup::new(left, right, Pairing::Timestamp(Duration::from_millis(5)));
+/// if let Some(Ok((left, right))) = stereo.next() {
+///     println!("{} / {} bytes", left.as_bytes().len(), right.as_bytes().len());
+/// }
+/// # }
+/// ```
+pub struct SyncGroup<A, B> {
+    a: A,
+    b: B,
+    pairing: Pairing,
+    pending_a: Option<Buffer<'static>>,
+    pending_b: Option<Buffer<'static>>,
+    dropped: (u64, u64),
+}
+
+impl<A, B> SyncGroup<A, B> {
+    /// Creates a new group pairing the frames of `a` and `b`
+    pub fn new(a: A, b: B, pairing: Pairing) -> Self {
+        SyncGroup {
+            a,
+            b,
+            pairing,
+            pending_a: None,
+            pending_b: None,
+            dropped: (0, 0),
+        }
+    }
+
+    /// Returns the number of frames of each stream which were discarded for lack of a partner
+    pub fn dropped(&self) -> (u64, u64) {
+        self.dropped
+    }
+
+    /// Returns the wrapped streams
+    pub fn into_inner(self) -> (A, B) {
+        (self.a, self.b)
+    }
+
+    /// Returns whether the frame of `a` belongs to the frame of `b`, or which one is older
+    fn compare(&self, a: &Buffer, b: &Buffer) -> Result<Order> {
+        match self.pairing {
+            Pairing::Sequence => {
+                let (a, b) = match (a.meta().sequence, b.meta().sequence) {
+                    (Some(a), Some(b)) => (a, b),
+                    _ => {
+                        return Err(Error::new(
+                            ErrorKind::NotSupported,
+                            "frames do not have sequence numbers",
+                        ))
+                    }
+                };
+
+                // wrapping arithmetic keeps this correct when the counters overflow
+                Ok(match b.wrapping_sub(a) as i32 {
+                    0 => Order::Paired,
+                    diff if diff > 0 => Order::AOlder,
+                    _ => Order::BOlder,
+                })
+            }
+            Pairing::Timestamp(tolerance) => {
+                let (a, b) = match (a.meta().capture_time, b.meta().capture_time) {
+                    (Some(a), Some(b)) => (a, b),
+                    _ => {
+                        return Err(Error::new(
+                            ErrorKind::NotSupported,
+                            "frames do not have capture times",
+                        ))
+                    }
+                };
+
+                Ok(if a.max(b) - a.min(b) <= tolerance {
+                    Order::Paired
+                } else if a < b {
+                    Order::AOlder
+                } else {
+                    Order::BOlder
+                })
+            }
+        }
+    }
+}
+
+enum Order {
+    Paired,
+    AOlder,
+    BOlder,
+}
+
+/// Returns the next frame of a stream, copied so the stream can be advanced
+fn fetch<S>(stream: &mut S) -> Option<Result<Buffer<'static>>>
+where
+    S: for<'a> Stream<'a, Item = Result<Buffer<'a>>>,
+{
+    Some(stream.next()?.map(|buf| buf.own()))
+}
+
+impl<'a, A, B> Stream<'a> for SyncGroup<A, B>
+where
+    A: for<'b> Stream<'b, Item = Result<Buffer<'b>>>,
+    B: for<'b> Stream<'b, Item = Result<Buffer<'b>>>,
+{
+    type Item = Result<(Buffer<'a>, Buffer<'a>)>;
+
+    fn next(&'a mut self) -> Option<Self::Item> {
+        loop {
+            let a = match self.pending_a.take() {
+                Some(buf) => buf,
+                None => match fetch(&mut self.a)? {
+                    Ok(buf) => buf,
+                    Err(e) => return Some(Err(e)),
+                },
+            };
+            let b = match self.pending_b.take() {
+                Some(buf) => buf,
+                None => match fetch(&mut self.b) {
+                    Some(Ok(buf)) => buf,
+                    Some(Err(e)) => {
+                        self.pending_a = Some(a);
+                        return Some(Err(e));
+                    }
+                    None => return None,
+                },
+            };
+
+            match self.compare(&a, &b) {
+                Ok(Order::Paired) => return Some(Ok((a, b))),
+                // The older frame has no partner, since the partner would have to be older than
+                // the frame of the other stream as well.
+                Ok(Order::AOlder) => {
+                    self.dropped.0 += 1;
+                    self.pending_b = Some(b);
+                }
+                Ok(Order::BOlder) => {
+                    self.dropped.1 += 1;
+                    self.pending_a = Some(a);
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+
+    fn set_interval(&mut self, interval: Duration) -> Result<()> {
+        self.a.set_interval(interval)?;
+        self.b.set_interval(interval)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.a.pause()?;
+        self.b.pause()?;
+        // the frames were captured before the pause
+        self.pending_a = None;
+        self.pending_b = None;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.a.resume()?;
+        self.b.resume()
+    }
+
+    fn is_paused(&self) -> bool {
+        self.a.is_paused() && self.b.is_paused()
+    }
+}
@@ -0,0 +1,102 @@
+use std::time::{Duration, Instant};
+
+use eye_hal::error::Result;
+use eye_hal::stream::{Descriptor, QueueState};
+use eye_hal::traits::Stream;
+
+/// A stream attaching a user defined tag to its items
+///
+/// When handling many streams in one place (e.g. one per camera), the tag tells where an item
+/// came from without keeping track of the streams separately.
+///
+/// # Example
+///
+/// ```no_run
+/// # use eye::hal::{platform, traits::Stream};
+/// # fn example(stream: platform::Stream) {
+/// use eye::stream::TaggedStream;
+///
+/// let mut stream = TaggedStream::new(stream, "front");
+/// if let Some((camera, Ok(buf))) = stream.next() {
+///     println!("{}: {} bytes", camera, buf.as_bytes().len());
+/// }
+/// # }
+/// ```
+pub struct TaggedStream<S, T> {
+    inner: S,
+    tag: T,
+}
+
+impl<S, T> TaggedStream<S, T> {
+    /// Creates a new stream tagging all items of `inner` with `tag`
+    pub fn new(inner: S, tag: T) -> Self {
+        TaggedStream { inner, tag }
+    }
+
+    /// Returns the tag
+    pub fn tag(&self) -> &T {
+        &self.tag
+    }
+
+    /// Returns the wrapped stream
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<'a, S, T> Stream<'a> for TaggedStream<S, T>
+where
+    S: Stream<'a>,
+    T: Clone,
+{
+    type Item = (T, S::Item);
+
+    fn next(&'a mut self) -> Option<Self::Item> {
+        let item = self.inner.next()?;
+        Some((self.tag.clone(), item))
+    }
+
+    fn buffer_count(&self) -> Option<usize> {
+        self.inner.buffer_count()
+    }
+
+    fn last_buffer_index(&self) -> Option<u32> {
+        self.inner.last_buffer_index()
+    }
+
+    fn queue_state(&self) -> Option<QueueState> {
+        self.inner.queue_state()
+    }
+
+    fn start_instant(&self) -> Option<Instant> {
+        self.inner.start_instant()
+    }
+
+    fn first_frame_latency(&self) -> Option<Duration> {
+        self.inner.first_frame_latency()
+    }
+
+    fn descriptor(&self) -> Option<Descriptor> {
+        self.inner.descriptor()
+    }
+
+    fn set_interval(&mut self, interval: Duration) -> Result<()> {
+        self.inner.set_interval(interval)
+    }
+
+    fn set_buffer_count(&mut self, count: usize) -> Result<()> {
+        self.inner.set_buffer_count(count)
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        self.inner.pause()
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        self.inner.resume()
+    }
+
+    fn is_paused(&self) -> bool {
+        self.inner.is_paused()
+    }
+}
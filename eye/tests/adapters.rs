@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use eye::hal::buffer::Buffer;
+use eye::hal::format::PixelFormat;
+use eye::hal::platform::{self, scripted, scripted::Event};
+use eye::hal::stream::{Descriptor, DeviceStreamSettings};
+use eye::hal::traits::{Device, Stream};
+use eye::hal::Result;
+use eye::stream::{sync::Pairing, Dedupe, GapFill, SyncGroup};
+
+/// Starts a stream of 16x16 grayscale frames which replays `events`
+fn scripted(events: Vec<Event>) -> platform::Stream<'static> {
+    let desc = Descriptor {
+        width: 16,
+        height: 16,
+        pixfmt: PixelFormat::Gray(8),
+        interval: Duration::from_millis(33),
+        emulated: false,
+    };
+    let dev = scripted::device::Handle::new(desc.clone(), events);
+    dev.start_stream(DeviceStreamSettings::new(&desc)).unwrap()
+}
+
+/// Returns a frame whose pixels all have the given value
+fn frame(value: u8) -> Event {
+    Event::Frame(vec![value; 16 * 16])
+}
+
+/// Returns the sequence number and first pixel value of the next frame
+fn next<S>(stream: &mut S) -> Option<(Option<u32>, u8)>
+where
+    S: for<'a> Stream<'a, Item = Result<Buffer<'a>>>,
+{
+    let buf = stream.next()?.unwrap();
+    Some((buf.meta().sequence, buf.as_bytes()[0]))
+}
+
+#[test]
+fn gap_fill_across_sequence_wrap() {
+    let mut stream = GapFill::duplicate(scripted(vec![
+        Event::Gap(u32::MAX - 1),
+        frame(1),
+        frame(2),
+        // the frame with sequence number 0 is missing
+        Event::Gap(1),
+        frame(3),
+    ]));
+
+    assert_eq!(next(&mut stream), Some((Some(u32::MAX - 1), 1)));
+    assert_eq!(next(&mut stream), Some((Some(u32::MAX), 2)));
+    assert_eq!(next(&mut stream), Some((Some(0), 2)));
+    assert_eq!(next(&mut stream), Some((Some(1), 3)));
+    assert_eq!(next(&mut stream), None);
+}
+
+#[test]
+fn sync_group_skips_dropped_frame() {
+    let a = scripted(vec![frame(0), frame(1), frame(2), frame(3)]);
+    let b = scripted(vec![frame(0), Event::Gap(1), frame(2), frame(3)]);
+    let mut group = SyncGroup::new(a, b, Pairing::Sequence);
+
+    let mut pairs = Vec::new();
+    while let Some(pair) = group.next() {
+        let (a, b) = pair.unwrap();
+        pairs.push((a.meta().sequence, b.meta().sequence));
+    }
+    assert_eq!(
+        pairs,
+        vec![(Some(0), Some(0)), (Some(2), Some(2)), (Some(3), Some(3))]
+    );
+    assert_eq!(group.dropped(), (1, 0));
+}
+
+#[test]
+fn sync_group_orders_sequences_across_wrap() {
+    let a = scripted(vec![Event::Gap(u32::MAX), frame(0), frame(1)]);
+    // the frame with sequence number u32::MAX is missing
+    let b = scripted(vec![Event::Gap(u32::MAX), Event::Gap(1), frame(1)]);
+    let mut group = SyncGroup::new(a, b, Pairing::Sequence);
+
+    let (a, b) = group.next().unwrap().unwrap();
+    assert_eq!((a.meta().sequence, b.meta().sequence), (Some(0), Some(0)));
+    assert!(group.next().is_none());
+    assert_eq!(group.dropped(), (1, 0));
+}
+
+#[test]
+fn dedupe_compares_to_last_passed_frame() {
+    let mut stream = Dedupe::new(scripted(vec![frame(10), frame(12), frame(14)]), 3.0);
+
+    assert_eq!(next(&mut stream), Some((Some(0), 10)));
+    // 12 is close enough to 10, but 14 is not
+    assert_eq!(next(&mut stream), Some((Some(2), 14)));
+    assert_eq!(next(&mut stream), None);
+    assert_eq!(stream.skipped(), 1);
+}
+
+#[test]
+fn dedupe_resets_on_pause() {
+    let mut stream = Dedupe::new(scripted(vec![frame(100), frame(100), frame(100)]), 3.0);
+
+    assert_eq!(next(&mut stream), Some((Some(0), 100)));
+    stream.pause().unwrap();
+    assert_eq!(next(&mut stream), None);
+    stream.resume().unwrap();
+    // the scene may have changed in the meantime, so the first frame is passed on
+    assert_eq!(next(&mut stream), Some((Some(1), 100)));
+    assert_eq!(next(&mut stream), None);
+    assert_eq!(stream.skipped(), 1);
+}